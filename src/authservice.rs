@@ -4,11 +4,17 @@ use crate::domain::*;
 use yew::prelude::*;
 use stdweb::{ js, serde::Serde, };
 use serde::{ Serialize, Deserialize };
+use crate::services::*;
+use crate::localauth::*;
+use crate::config::*;
 
-#[derive(Serialize,Deserialize,Debug)]
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
 pub struct AuthUser {
     pub name: String,
     pub token: String,
+    // When the token stops working, in ms since the epoch.
+    #[serde(default)]
+    pub expires_at: Option<f64>,
 }
 
 #[derive(Serialize,Deserialize,Debug)]
@@ -18,42 +24,61 @@ pub enum AuthState {
     No,
 }
 
-pub struct AuthService;
+impl AuthState {
+    pub fn token(&self) -> Option<String> {
+        match self {
+            AuthState::Yes(user) => Some(user.token.clone()),
+            _                    => None,
+        }
+    }
+}
+
+pub fn provider(config: &AuthConfig) -> Box<dyn Auth> {
+    match config {
+        AuthConfig::Auth0(auth0) => Box::new(Auth0Provider::new(auth0.clone())),
+        AuthConfig::Local(local) => Box::new(LocalProvider::new(local.clone(), Box::new(WebStorage::new()))),
+    }
+}
+
+// Auth0's Universal Login, through the auth0-spa-js client loaded by
+// index.html.
+pub struct Auth0Provider {
+    config: Auth0Config,
+}
 
-impl AuthService {
-    pub fn new() -> Self {
-        AuthService
+impl Auth0Provider {
+    pub fn new(config: Auth0Config) -> Self {
+        Self { config }
     }
-    
-    pub fn init(&mut self, msg_callback: Callback<AuthState>) {
+}
+
+impl Auth for Auth0Provider {
+    fn init(&mut self, msg_callback: Callback<AuthState>) {
         let callback = move |x: Serde<AuthState>| {
             msg_callback.emit(x.0); 
         };
         
         // Simplifies getting the right structure into JS.
-        let default_yes = Serde(AuthState::Yes(
-            AuthUser {
-                name:  "".into(),
-                token: "".into(),
-            }
-        ));
+        let default_yes = Serde(AuthState::Yes(AuthUser::default()));
+        let config = Serde(self.config.clone());
 
         js! {
             const callback = @{callback};
+            const config = @{config};
 
             const f = async () => {
                 let payload = null;
-                auth0 = await createAuth0Client(auth_config);
+                auth0 = await createAuth0Client(config);
                 const authed = await auth0.isAuthenticated();
                 const query = window.location.search;
 
                 const auth_yes = async () => {
                     payload = @{default_yes}; 
-                    const user  = await auth0.getUser();
-                    const token = (await auth0.getIdTokenClaims()).__raw;
-                    console.log(token);
+                    const user   = await auth0.getUser();
+                    const claims = await auth0.getIdTokenClaims();
                     payload.Yes.name = user.name;
-                    payload.Yes.token = token;
+                    payload.Yes.token = claims.__raw;
+                    payload.Yes.expires_at = claims.exp * 1000;
                 };
                             
                 const auth_no = async () => {
@@ -95,7 +120,7 @@ impl AuthService {
         };
     }
 
-    pub fn login(&mut self) {
+    fn login(&mut self) {
         js! {
             if (auth0 == null) {
                 return;
@@ -111,7 +136,43 @@ impl AuthService {
         };
     }
 
-    pub fn logout(&mut self) {
+    fn refresh(&mut self, msg_callback: Callback<AuthState>) {
+        let callback = move |x: Serde<AuthState>| {
+            msg_callback.emit(x.0);
+        };
+
+        let default_yes = Serde(AuthState::Yes(AuthUser::default()));
+
+        js! {
+            const callback = @{callback};
+
+            const f = async () => {
+                let payload = @{Serde(AuthState::No)};
+
+                try {
+                    if (auth0 != null) {
+                        await auth0.getTokenSilently({ ignoreCache: true });
+                        const user   = await auth0.getUser();
+                        const claims = await auth0.getIdTokenClaims();
+                        payload = @{default_yes};
+                        payload.Yes.name = user.name;
+                        payload.Yes.token = claims.__raw;
+                        payload.Yes.expires_at = claims.exp * 1000;
+                    }
+                }
+                catch (e) {
+                    console.log(e);
+                }
+
+                callback(payload);
+                callback.drop();
+            };
+
+            f();
+        };
+    }
+
+    fn logout(&mut self) {
         js! {
             if (auth0 == null) {
                 return;
@@ -0,0 +1,7 @@
+// Prints the API contract as an OpenAPI document, for the backend to
+// check itself against: `cargo run --bin api_schema > openapi.json`.
+
+fn main() {
+    let doc = frontend::contract::openapi();
+    println!("{}", serde_json::to_string_pretty(&doc).unwrap());
+}
@@ -0,0 +1,838 @@
+// Stand-in for the real API, for developing the frontend offline.
+//
+//     cargo run --bin mock_backend -- [--port 8001] [--state state.json]
+//
+// Serves every /api/* call the frontend makes from in-memory state seeded
+// with fixtures/mock_seed.json. With --state, the state is loaded from that
+// file if it exists and written back after every change.
+// The caller is whichever seeded user the bearer token names, e.g.
+// "Bearer local|viewer"; any other token, or none, is the first user.
+// Nothing is enforced, get_me just reports who that is.
+// Writes that name an older version of a project or task than the one
+// stored get a 409 and change nothing.
+// /api/events is a WebSocket: every change is sent to everyone connected
+// as Events, the way the frontend expects (see contract::EVENTS_PATH).
+
+use std::io::{ BufRead, BufReader, Read, Write };
+use std::net::{ TcpListener, TcpStream };
+use std::time::Duration;
+use serde::{ Serialize, Deserialize };
+use serde::de::DeserializeOwned;
+use frontend::domain::*;
+use frontend::contract::EVENTS_PATH;
+use frontend::liststate::ListState;
+
+const SEED: &str = include_str!("../../fixtures/mock_seed.json");
+
+// Connections are served one at a time, so one that sends nothing (a
+// browser's preconnect, or an idle keep-alive) is given up on after this
+// rather than holding up the rest.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+struct Project {
+    id: ProjectId,
+    name: ProjectName,
+    #[serde(default)]
+    version: Version,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+struct Worker {
+    id: WorkerId,
+    name: WorkerName,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone)]
+struct Task {
+    id: TaskId,
+    name: TaskName,
+    project_id: ProjectId,
+    worker_id: Option<WorkerId>,
+    #[serde(default)]
+    version: Version,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Default)]
+struct Store {
+    projects: Vec<Project>,
+    workers: Vec<Worker>,
+    tasks: Vec<Task>,
+    users: Vec<User>,
+    // Users hold copies; granting copies from here.
+    #[serde(default)]
+    roles: Vec<Role>,
+    // What changed since they were last sent out.
+    #[serde(skip)]
+    events: Vec<Event>,
+}
+
+// What a call produced: the JSON for `Result<T, String>`, and whether
+// it changed anything worth saving.
+struct Reply {
+    status: u16,
+    body: String,
+    changed: bool,
+}
+
+fn ok<T: Serialize>(value: T) -> Reply {
+    let result: Result<T, String> = Ok(value);
+    Reply {
+        status: 200,
+        body: serde_json::to_string(&result).unwrap(),
+        changed: false,
+    }
+}
+
+fn changed<T: Serialize>(value: T) -> Reply {
+    Reply { changed: true, ..ok(value) }
+}
+
+fn err(msg: &str) -> Reply {
+    let result: Result<(), String> = Err(msg.into());
+    Reply {
+        status: 200,
+        body: serde_json::to_string(&result).unwrap(),
+        changed: false,
+    }
+}
+
+fn stale() -> Reply {
+    let result: Result<(), String> = Err("changed by someone else".into());
+    Reply {
+        status: 409,
+        body: serde_json::to_string(&result).unwrap(),
+        changed: false,
+    }
+}
+
+fn not_found() -> Reply {
+    Reply { status: 404, body: String::new(), changed: false }
+}
+
+fn page<T: Clone>(items: &[T], offset: usize, limit: usize) -> Page<T> {
+    Page {
+        items: items.iter().skip(offset).take(limit).cloned().collect(),
+        offset,
+        total: items.len(),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T, Reply> {
+    serde_json::from_str(body).map_err(|_| err("bad request body"))
+}
+
+impl Store {
+    fn seeded() -> Self {
+        serde_json::from_str(SEED).expect("bad seed fixture")
+    }
+
+    fn next_id<T>(items: &[T], id: impl Fn(&T) -> i32) -> i32 {
+        items.iter().map(id).max().unwrap_or(0) + 1
+    }
+
+    fn list_task(&self, task: &Task) -> ListTask {
+        ListTask {
+            id: task.id,
+            name: task.name.clone(),
+            status: match task.worker_id {
+                Some(_) => TaskStatus::Assigned,
+                None    => TaskStatus::Unassigned,
+            },
+        }
+    }
+
+    fn list_project(&self, project: &Project) -> ListProject {
+        ListProject {
+            id: project.id,
+            name: project.name.clone(),
+            task_count: self.tasks.iter().filter(|t| t.project_id == project.id).count(),
+        }
+    }
+
+    fn list_worker(&self, worker: &Worker) -> ListWorker {
+        ListWorker {
+            id: worker.id,
+            name: worker.name.clone(),
+            task_count: self.tasks.iter().filter(|t| t.worker_id == Some(worker.id)).count(),
+        }
+    }
+
+    fn list_projects(&self) -> Vec<ListProject> {
+        self.projects.iter().map(|p| self.list_project(p)).collect()
+    }
+
+    fn list_workers(&self) -> Vec<ListWorker> {
+        self.workers.iter().map(|w| self.list_worker(w)).collect()
+    }
+
+    fn project_details(&self, id: ProjectId) -> Option<ProjectDetails> {
+        self.projects.iter().find(|p| p.id == id).map(|p| ProjectDetails {
+            id: p.id,
+            name: p.name.clone(),
+            version: p.version,
+            tasks: self.tasks.iter()
+                .filter(|t| t.project_id == id)
+                .map(|t| self.list_task(t))
+                .collect(),
+        })
+    }
+
+    fn worker_details(&self, id: WorkerId) -> Option<WorkerDetails> {
+        self.workers.iter().find(|w| w.id == id).map(|w| WorkerDetails {
+            id: w.id,
+            name: w.name.clone(),
+            tasks: self.tasks.iter()
+                .filter(|t| t.worker_id == Some(id))
+                .map(|t| self.list_task(t))
+                .collect(),
+            // Nothing writes to a worker itself.
+            version: 0,
+        })
+    }
+
+    fn task_details(&self, id: TaskId) -> Option<TaskDetails> {
+        let task = self.tasks.iter().find(|t| t.id == id)?;
+        let project = self.projects.iter().find(|p| p.id == task.project_id)?;
+        let worker = task.worker_id
+            .and_then(|wid| self.workers.iter().find(|w| w.id == wid));
+
+        Some(TaskDetails {
+            id: task.id,
+            name: task.name.clone(),
+            project: self.list_project(project),
+            worker: worker.map(|w| self.list_worker(w)),
+            version: task.version,
+        })
+    }
+
+    fn task_mut(&mut self, id: TaskId, version: Version) -> Result<&mut Task, Reply> {
+        let task = self.tasks.iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| err("no such task"))?;
+        if task.version != version {
+            return Err(stale());
+        }
+        task.version += 1;
+        Ok(task)
+    }
+
+    // The task, and the lists it's in before and after a change.
+    fn task_changed(&mut self, id: TaskId, was_with: Option<WorkerId>) {
+        let task = match self.task_details(id) {
+            Some(task) => task,
+            None       => return,
+        };
+        let mut workers: Vec<WorkerId> = was_with.into_iter().chain(task.worker.as_ref().map(|w| w.id)).collect();
+        workers.dedup();
+
+        self.events.extend(self.project_details(task.project.id).map(|p| Event::Updated(Entity::Project(p))));
+        for worker in workers {
+            self.events.extend(self.worker_details(worker).map(|w| Event::Updated(Entity::Worker(w))));
+        }
+        self.events.push(Event::Updated(Entity::Task(task)));
+    }
+
+    fn worker_of(&self, task_id: TaskId) -> Option<WorkerId> {
+        self.tasks.iter().find(|t| t.id == task_id).and_then(|t| t.worker_id)
+    }
+
+    fn user_mut(&mut self, id: &str) -> Result<&mut User, Reply> {
+        self.users.iter_mut()
+            .find(|u| u.id == id)
+            .ok_or_else(|| err("no such user"))
+    }
+
+    fn caller(&self, token: Option<&str>) -> Option<User> {
+        token
+            .and_then(|token| self.users.iter().find(|u| u.id == token))
+            .or_else(|| self.users.first())
+            .cloned()
+    }
+
+    fn handle(&mut self, token: Option<&str>, method: &str, path: &str, body: &str) -> Reply {
+        match self.route(token, method, path, body) {
+            Ok(reply) => reply,
+            Err(reply) => reply,
+        }
+    }
+
+    fn route(&mut self, token: Option<&str>, method: &str, path: &str, body: &str) -> Result<Reply, Reply> {
+        // Filters and sorts paged lists, the way the frontend would.
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let list = ListState::from_query(query);
+        let segments: Vec<&str> = path
+            .trim_start_matches('/')
+            .split('/')
+            .collect();
+        let num = |s: &str| s.parse::<i32>().map_err(|_| not_found());
+        let size = |s: &str| s.parse::<usize>().map_err(|_| not_found());
+
+        let reply = match (method, segments.as_slice()) {
+            ("GET", ["api", "get_projects"]) =>
+                ok(self.list_projects()),
+            ("GET", ["api", "get_workers"]) =>
+                ok(self.list_workers()),
+            ("GET", ["api", "get_users"]) =>
+                ok(&self.users),
+            ("GET", ["api", "get_me"]) =>
+                ok(self.caller(token)),
+            ("GET", ["api", "get_roles"]) =>
+                ok(&self.roles),
+            ("GET", ["api", "get_projects_page", offset, limit]) =>
+                ok(page(&list.apply(&self.list_projects()), size(offset)?, size(limit)?)),
+            ("GET", ["api", "get_workers_page", offset, limit]) =>
+                ok(page(&list.apply(&self.list_workers()), size(offset)?, size(limit)?)),
+            ("GET", ["api", "get_users_page", offset, limit]) =>
+                ok(page(&list.apply(&self.users), size(offset)?, size(limit)?)),
+            ("GET", ["api", "get_project", id]) =>
+                ok(self.project_details(num(id)?)),
+            ("GET", ["api", "get_worker", id]) =>
+                ok(self.worker_details(num(id)?)),
+            ("GET", ["api", "get_task", id]) =>
+                ok(self.task_details(num(id)?)),
+            ("POST", ["api", "add_project"]) => {
+                let params: AddProjectParams = parse_body(body)?;
+                let id = Self::next_id(&self.projects, |p| p.id);
+                self.projects.push(Project { id, name: params.name, version: 0 });
+                self.events.extend(self.project_details(id).map(|p| Event::Created(Entity::Project(p))));
+                changed(id)
+            }
+            ("POST", ["api", "add_worker"]) => {
+                let params: AddWorkerParams = parse_body(body)?;
+                let id = Self::next_id(&self.workers, |w| w.id);
+                self.workers.push(Worker { id, name: params.name });
+                self.events.extend(self.worker_details(id).map(|w| Event::Created(Entity::Worker(w))));
+                changed(id)
+            }
+            ("POST", ["api", "add_task"]) => {
+                let params: AddTaskParams = parse_body(body)?;
+                let project = self.projects.iter_mut()
+                    .find(|p| p.id == params.project_id)
+                    .ok_or_else(|| err("no such project"))?;
+                if project.version != params.version {
+                    return Err(stale());
+                }
+                project.version += 1;
+                let id = Self::next_id(&self.tasks, |t| t.id);
+                self.tasks.push(Task {
+                    id,
+                    name: params.name,
+                    project_id: params.project_id,
+                    worker_id: None,
+                    version: 0,
+                });
+                // New tasks start unassigned, so only the project is updated.
+                self.events.extend(self.task_details(id).map(|t| Event::Created(Entity::Task(t))));
+                self.events.extend(self.project_details(params.project_id).map(|p| Event::Updated(Entity::Project(p))));
+                changed(id)
+            }
+            ("POST", ["api", "assign_task"]) => {
+                let params: AssignTaskParams = parse_body(body)?;
+                if !self.workers.iter().any(|w| w.id == params.worker_id) {
+                    return Err(err("no such worker"));
+                }
+                let was_with = self.worker_of(params.task_id);
+                self.task_mut(params.task_id, params.version)?.worker_id = Some(params.worker_id);
+                self.task_changed(params.task_id, was_with);
+                changed(())
+            }
+            ("POST", ["api", "unassign_task"]) => {
+                let params: UnassignTaskParams = parse_body(body)?;
+                let was_with = self.worker_of(params.task_id);
+                self.task_mut(params.task_id, params.version)?.worker_id = None;
+                self.task_changed(params.task_id, was_with);
+                changed(())
+            }
+            ("POST", ["api", "grant_role"]) => {
+                let params: GrantRoleParams = parse_body(body)?;
+                let role = self.roles.iter()
+                    .find(|r| r.id == params.role_id)
+                    .cloned()
+                    .ok_or_else(|| err("no such role"))?;
+                let user = self.user_mut(&params.user_id)?;
+                if !user.has_role(&role.id) {
+                    user.roles.push(role);
+                }
+                changed(())
+            }
+            ("POST", ["api", "link_worker"]) => {
+                let params: LinkWorkerParams = parse_body(body)?;
+                if let Some(id) = params.worker_id {
+                    if !self.workers.iter().any(|w| w.id == id) {
+                        return Err(err("no such worker"));
+                    }
+                }
+                self.user_mut(&params.user_id)?.worker_id = params.worker_id;
+                changed(())
+            }
+            ("POST", ["api", "revoke_role"]) => {
+                let params: RevokeRoleParams = parse_body(body)?;
+                let user = self.user_mut(&params.user_id)?;
+                user.roles.retain(|r| r.id != params.role_id);
+                changed(())
+            }
+            _ => not_found(),
+        };
+
+        Ok(reply)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    token: Option<String>,
+    // Sec-WebSocket-Key, when asking for one.
+    websocket_key: Option<String>,
+    body: String,
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut length = 0;
+    let mut token = None;
+    let mut websocket_key = None;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+            if name.eq_ignore_ascii_case("authorization") {
+                token = value.trim().strip_prefix("Bearer ").map(String::from);
+            }
+            if name.eq_ignore_ascii_case("sec-websocket-key") {
+                websocket_key = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        token,
+        websocket_key,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+fn write_response(mut stream: &TcpStream, status: u16, body: &str) {
+    let reason = match status {
+        200 => "OK",
+        204 => "No Content",
+        404 => "Not Found",
+        409 => "Conflict",
+        _   => "Error",
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+         Connection: close\r\n\
+         \r\n\
+         {}",
+        status, reason, body.len(), body
+        );
+
+    let _ = stream.write_all(response.as_bytes());
+}
+
+// The handshake's answer to `key` (RFC 6455, 4.2.2).
+fn websocket_accept(key: &str) -> String {
+    let digest = sha1::Sha1::from(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key)).digest().bytes();
+    base64(&digest)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+            else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn write_upgrade(mut stream: &TcpStream, key: &str) -> bool {
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\
+         \r\n",
+        websocket_accept(key)
+        );
+
+    stream.write_all(response.as_bytes()).is_ok()
+}
+
+// An unmasked text frame, as a server sends them.
+fn text_frame(text: &str) -> Vec<u8> {
+    let mut frame = vec![0x81];
+    let len = text.len();
+    if len < 126 {
+        frame.push(len as u8);
+    }
+    else if len <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend_from_slice(&(len as u16).to_be_bytes());
+    }
+    else {
+        frame.push(127);
+        frame.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    frame.extend_from_slice(text.as_bytes());
+    frame
+}
+
+// Sends what changed to everyone listening, and forgets whoever's gone.
+fn broadcast(listeners: &mut Vec<TcpStream>, events: Vec<Event>) {
+    for event in events {
+        let frame = text_frame(&serde_json::to_string(&event).unwrap());
+        listeners.retain(|mut listener| listener.write_all(&frame).is_ok());
+    }
+}
+
+struct Args {
+    port: u16,
+    state: Option<String>,
+}
+
+fn parse_args() -> Args {
+    let mut args = Args { port: 8001, state: None };
+    let mut iter = std::env::args().skip(1);
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--port"  => args.port = iter.next().and_then(|p| p.parse().ok()).expect("--port needs a number"),
+            "--state" => args.state = Some(iter.next().expect("--state needs a path")),
+            _         => panic!("unknown argument {}", arg),
+        }
+    }
+
+    args
+}
+
+fn load(state: &Option<String>) -> Store {
+    state.as_ref()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|json| serde_json::from_str(&json).expect("bad state file"))
+        .unwrap_or_else(Store::seeded)
+}
+
+fn save(state: &Option<String>, store: &Store) {
+    if let Some(path) = state {
+        let json = serde_json::to_string_pretty(store).unwrap();
+        if let Err(e) = std::fs::write(path, json) {
+            eprintln!("could not save {}: {}", path, e);
+        }
+    }
+}
+
+fn main() {
+    let args = parse_args();
+    let mut store = load(&args.state);
+    let listener = TcpListener::bind(("127.0.0.1", args.port)).expect("could not bind");
+    println!("mock backend on http://localhost:{}", args.port);
+    let mut listeners = Vec::new();
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_)     => continue,
+        };
+        if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+            continue;
+        }
+        let req = match read_request(&stream) {
+            Some(req) => req,
+            None      => continue,
+        };
+
+        if req.method == "OPTIONS" {
+            write_response(&stream, 204, "");
+            continue;
+        }
+        if let (Some(key), true) = (&req.websocket_key, req.path == format!("/api/{}", EVENTS_PATH)) {
+            println!("{} {} -> 101", req.method, req.path);
+            if write_upgrade(&stream, key) {
+                listeners.push(stream);
+            }
+            continue;
+        }
+
+        let reply = store.handle(req.token.as_deref(), &req.method, &req.path, &req.body);
+        println!("{} {} -> {}", req.method, req.path, reply.status);
+
+        if reply.changed {
+            save(&args.state, &store);
+        }
+        write_response(&stream, reply.status, &reply.body);
+        broadcast(&mut listeners, std::mem::take(&mut store.events));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Display;
+    use frontend::contract::{ self, Endpoint, Method };
+
+    fn get<T: DeserializeOwned>(store: &mut Store, path: &str) -> T {
+        let reply = store.handle(None, "GET", path, "");
+        assert_eq!(reply.status, 200, "{}", path);
+        let result: Result<T, String> = serde_json::from_str(&reply.body).unwrap();
+        result.unwrap()
+    }
+
+    fn post(store: &mut Store, path: &str, body: &str) -> Reply {
+        store.handle(None, "POST", path, body)
+    }
+
+    #[test]
+    fn paged_lists_are_filtered_and_sorted_before_paging() {
+        let mut store = Store::seeded();
+
+        let projects: Page<ListProject> = get(&mut store, "/api/get_projects_page/0/1?q=i&sort=id&desc");
+        assert_eq!(projects.total, 3);
+        assert_eq!(projects.items[0].name, "Billing");
+
+        let projects: Page<ListProject> = get(&mut store, "/api/get_projects_page/0/50?q=mob");
+        assert_eq!(projects.total, 1);
+        assert_eq!(projects.items[0].name, "Mobile app");
+    }
+
+    #[test]
+    fn seed_serves_every_read() {
+        let mut store = Store::seeded();
+
+        let projects: Page<ListProject> = get(&mut store, "/api/get_projects_page/0/50");
+        assert_eq!(projects.total, 3);
+        assert_eq!(projects.items[0].task_count, 2);
+
+        let workers: Page<ListWorker> = get(&mut store, "/api/get_workers_page/1/1");
+        assert_eq!(workers.items.len(), 1);
+        assert_eq!(workers.offset, 1);
+
+        let users: Page<User> = get(&mut store, "/api/get_users_page/0/50");
+        assert_eq!(users.total, 2);
+
+        let me: Option<User> = get(&mut store, "/api/get_me");
+        let me = me.unwrap();
+        assert_eq!(me.id, "local|admin");
+        assert_eq!(me.worker_id, Some(1));
+        let reply = store.handle(Some("local|viewer"), "GET", "/api/get_me", "");
+        let viewer: Result<Option<User>, String> = serde_json::from_str(&reply.body).unwrap();
+        let perms = viewer.unwrap().unwrap().perms();
+        assert!(perms.has(Perm::View));
+        assert!(!perms.has(Perm::CreateProject));
+
+        let all: Vec<ListWorker> = get(&mut store, "/api/get_workers");
+        assert_eq!(all.len(), 3);
+
+        let project: Option<ProjectDetails> = get(&mut store, "/api/get_project/1");
+        assert_eq!(project.unwrap().tasks.len(), 2);
+
+        let worker: Option<WorkerDetails> = get(&mut store, "/api/get_worker/1");
+        assert_eq!(worker.unwrap().tasks.len(), 2);
+
+        let task: Option<TaskDetails> = get(&mut store, "/api/get_task/2");
+        assert!(task.unwrap().worker.is_none());
+
+        let missing: Option<TaskDetails> = get(&mut store, "/api/get_task/99");
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn mutations_change_state() {
+        let mut store = Store::seeded();
+
+        let reply = post(&mut store, "/api/add_task", r#"{"name":"Ship","project_id":2,"version":0}"#);
+        assert!(reply.changed);
+        let result: Result<TaskId, String> = serde_json::from_str(&reply.body).unwrap();
+        let id = result.unwrap();
+
+        post(&mut store, "/api/assign_task", &format!(r#"{{"task_id":{},"worker_id":3,"version":0}}"#, id));
+        let task: Option<TaskDetails> = get(&mut store, &format!("/api/get_task/{}", id));
+        let task = task.unwrap();
+        assert_eq!(task.worker.unwrap().id, 3);
+        assert_eq!(task.version, 1);
+
+        post(&mut store, "/api/unassign_task", &format!(r#"{{"task_id":{},"version":1}}"#, id));
+        let task: Option<TaskDetails> = get(&mut store, &format!("/api/get_task/{}", id));
+        assert!(task.unwrap().worker.is_none());
+
+        post(&mut store, "/api/grant_role", r#"{"user_id":"local|viewer","role_id":"planner"}"#);
+        post(&mut store, "/api/revoke_role", r#"{"user_id":"local|viewer","role_id":"viewer"}"#);
+        let users: Vec<User> = get(&mut store, "/api/get_users");
+        let roles: Vec<&str> = users[1].roles.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(roles, ["planner"]);
+
+        post(&mut store, "/api/link_worker", r#"{"user_id":"local|viewer","worker_id":2}"#);
+        let users: Vec<User> = get(&mut store, "/api/get_users");
+        assert_eq!(users[1].worker_id, Some(2));
+
+        post(&mut store, "/api/add_project", r#"{"name":"Ops"}"#);
+        post(&mut store, "/api/add_worker", r#"{"name":"Di"}"#);
+        let projects: Vec<ListProject> = get(&mut store, "/api/get_projects");
+        let workers: Vec<ListWorker> = get(&mut store, "/api/get_workers");
+        assert_eq!(projects.len(), 4);
+        assert_eq!(workers.len(), 4);
+    }
+
+    #[test]
+    fn bad_calls_are_rejected() {
+        let mut store = Store::seeded();
+
+        let reply = post(&mut store, "/api/add_task", r#"{"name":"Ship","project_id":99,"version":0}"#);
+        assert_eq!(reply.body, r#"{"Err":"no such project"}"#);
+        assert!(!reply.changed);
+
+        let reply = post(&mut store, "/api/grant_role", r#"{"user_id":"local|viewer","role_id":"owner"}"#);
+        assert_eq!(reply.body, r#"{"Err":"no such role"}"#);
+
+        let reply = post(&mut store, "/api/assign_task", "not json");
+        assert_eq!(reply.body, r#"{"Err":"bad request body"}"#);
+
+        let reply = post(&mut store, "/api/assign_task", r#"{"task_id":1,"worker_id":2,"version":0}"#);
+        assert!(reply.changed);
+        let reply = post(&mut store, "/api/assign_task", r#"{"task_id":1,"worker_id":3,"version":0}"#);
+        assert_eq!(reply.status, 409);
+        assert!(!reply.changed);
+        let task: Option<TaskDetails> = get(&mut store, "/api/get_task/1");
+        assert_eq!(task.unwrap().worker.unwrap().id, 2);
+
+        assert_eq!(store.handle(None, "GET", "/api/get_task/abc", "").status, 404);
+        assert_eq!(store.handle(None, "GET", "/api/nope", "").status, 404);
+    }
+
+    #[test]
+    fn changes_are_sent_as_events() {
+        let mut store = Store::seeded();
+
+        post(&mut store, "/api/assign_task", r#"{"task_id":2,"worker_id":3,"version":0}"#);
+        let ids: Vec<EntityRef> = store.events.iter().map(Event::id).collect();
+        assert_eq!(ids, [EntityRef::Project(1), EntityRef::Worker(3), EntityRef::Task(2)]);
+        match &store.events[2] {
+            Event::Updated(Entity::Task(task)) => assert_eq!(task.version, 1),
+            event => panic!("{:?}", event),
+        }
+
+        store.events.clear();
+        post(&mut store, "/api/assign_task", r#"{"task_id":2,"worker_id":3,"version":0}"#);
+        assert!(store.events.is_empty());
+
+        post(&mut store, "/api/add_project", r#"{"name":"Ops"}"#);
+        assert!(matches!(store.events[..], [Event::Created(Entity::Project(ref p))] if p.name == "Ops"));
+
+        store.events.clear();
+        post(&mut store, "/api/add_task", r#"{"name":"Ship","project_id":2,"version":0}"#);
+        assert!(matches!(store.events[..], [
+            Event::Created(Entity::Task(ref t)),
+            Event::Updated(Entity::Project(ref p)),
+        ] if t.name == "Ship" && p.id == 2));
+    }
+
+    #[test]
+    fn speaks_websocket() {
+        // The example in RFC 6455.
+        assert_eq!(websocket_accept("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(text_frame("hi"), [0x81, 2, b'h', b'i']);
+        assert_eq!(text_frame(&"x".repeat(300))[..4], [0x81, 126, 1, 44]);
+    }
+
+    // Calls E with real params, the way the frontend does, and checks the
+    // answer is an Ok that decodes as E::Response.
+    fn call<E: Endpoint>(store: &mut Store, args: &[&dyn Display], params: &E::Params, called: &mut Vec<&str>) -> E::Response {
+        let method = match E::METHOD {
+            Method::Get  => "GET",
+            Method::Post => "POST",
+        };
+        let path = format!("/api/{}", E::action(args));
+        let body = match E::METHOD {
+            Method::Get  => String::new(),
+            Method::Post => serde_json::to_string(params).unwrap(),
+        };
+        let reply = store.handle(None, method, &path, &body);
+        assert_eq!(reply.status, 200, "{} {}", method, path);
+        let result: Result<E::Response, String> = serde_json::from_str(&reply.body)
+            .unwrap_or_else(|e| panic!("{} {}: {}", method, path, e));
+        called.push(E::PATH);
+        result.unwrap_or_else(|e| panic!("{} {}: {}", method, path, e))
+    }
+
+    #[test]
+    fn serves_the_whole_contract() {
+        use contract::*;
+        let mut store = Store::seeded();
+        let called = &mut Vec::new();
+        let page: &[&dyn Display] = &[&0, &50];
+
+        call::<GetProjects>(&mut store, &[], &(), called);
+        call::<GetProjectsPage>(&mut store, page, &(), called);
+        call::<GetWorkers>(&mut store, &[], &(), called);
+        call::<GetWorkersPage>(&mut store, page, &(), called);
+        call::<GetUsers>(&mut store, &[], &(), called);
+        call::<GetUsersPage>(&mut store, page, &(), called);
+        call::<GetMe>(&mut store, &[], &(), called);
+        call::<GetRoles>(&mut store, &[], &(), called);
+        assert!(call::<GetProject>(&mut store, &[&1], &(), called).is_some());
+        assert!(call::<GetWorker>(&mut store, &[&1], &(), called).is_some());
+        assert!(call::<GetTask>(&mut store, &[&1], &(), called).is_some());
+
+        call::<AddProject>(&mut store, &[], &AddProjectParams { name: "Ship".into() }, called);
+        call::<AddWorker>(&mut store, &[], &AddWorkerParams { name: "Di".into() }, called);
+        let version = store.project_details(1).unwrap().version;
+        call::<AddTask>(&mut store, &[], &AddTaskParams { name: "Test".into(), project_id: 1, version }, called);
+        let version = store.task_details(1).unwrap().version;
+        call::<AssignTask>(&mut store, &[], &AssignTaskParams { task_id: 1, worker_id: 2, version }, called);
+        call::<UnassignTask>(&mut store, &[], &UnassignTaskParams { task_id: 1, version: version + 1 }, called);
+        let grant = GrantRoleParams { user_id: "local|viewer".into(), role_id: "planner".into() };
+        call::<GrantRole>(&mut store, &[], &grant, called);
+        let revoke = RevokeRoleParams { user_id: "local|viewer".into(), role_id: "planner".into() };
+        call::<RevokeRole>(&mut store, &[], &revoke, called);
+        let link = LinkWorkerParams { user_id: "local|viewer".into(), worker_id: Some(2) };
+        call::<LinkWorker>(&mut store, &[], &link, called);
+
+        let mut all: Vec<&str> = endpoints().iter().map(|endpoint| endpoint.path).collect();
+        all.sort();
+        called.sort();
+        assert_eq!(*called, all);
+    }
+
+    #[test]
+    fn bad_bodies_are_an_err() {
+        let mut store = Store::seeded();
+        let reply = post(&mut store, "/api/add_task", "{}");
+        let result: Result<TaskId, String> = serde_json::from_str(&reply.body).unwrap();
+        assert_eq!(result, Err("bad request body".into()));
+    }
+}
@@ -0,0 +1,759 @@
+// Project, worker and task details seen so far, by id. Going back to one
+// shows the cached copy at once while a fresh one loads (see
+// `fetch_cached!` in msg.rs). Mutations are applied here before the
+// server answers, and undone one by one if it says no (see `Undo`), so
+// the others in flight stay.
+//
+// A task shows up in three places: its own details, its project's list
+// and its worker's list. Storing or changing it updates all of them.
+//
+// Changes made elsewhere arrive as Events (see live.rs), the user's own
+// included, possibly before the save they echo has been answered.
+
+use std::collections::HashMap;
+use serde::{ Serialize, Deserialize };
+use crate::domain::*;
+use crate::views::*;
+
+#[derive(Default,Clone)]
+pub struct Cache {
+    projects: HashMap<ProjectId, ProjectDetails>,
+    workers: HashMap<WorkerId, WorkerDetails>,
+    tasks: HashMap<TaskId, TaskDetails>,
+    // Every worker seen in a list, to name them in task details.
+    worker_rows: HashMap<WorkerId, ListWorker>,
+    // Kept apart from the details, which may be dropped (see `assign`)
+    // while the task can still be written to.
+    task_versions: HashMap<TaskId, Version>,
+    // Counts `apply`s, so an answer asked for before one can tell it
+    // may not have it (see Msg::Polled).
+    edits: u32,
+    // Stands in for the ids of things not saved yet; see `unsaved_id`.
+    unsaved: i32,
+}
+
+// What a mutation does, as shown before the server has said yes. Anything
+// added goes by an id from `unsaved_id` until `Created` brings the real one.
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub enum Change {
+    ProjectAdded { project: ListProject },
+    WorkerAdded { worker: ListWorker },
+    TaskAdded { project_id: ProjectId, task: ListTask },
+    TaskAssigned { task_id: TaskId, worker_id: WorkerId },
+    TaskUnassigned { task_id: TaskId },
+    RoleGranted { user_id: UserId, role: Role },
+    RoleRevoked { user_id: UserId, role_id: RoleId },
+    // The server's id for something added as `unsaved`.
+    Created { unsaved: i32, id: i32 },
+}
+
+// How to take a change back, worked out before it's applied (see
+// `Cache::undo`). Only that change is undone: anything made or received
+// since stays.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Undo {
+    // Drops what was added as `unsaved`. A task also gives back the
+    // version it took from its project.
+    Remove { unsaved: i32, project_id: Option<ProjectId> },
+    // Gives the task back to whoever had it, and its details back if
+    // they were dropped (see `assign`).
+    Reassign { task_id: TaskId, worker_id: Option<WorkerId>, details: Option<TaskDetails> },
+    // Who had the task wasn't known. It was assigned to someone, though
+    // (see `holder`), so its details are dropped to be fetched again.
+    Forget { task_id: TaskId },
+    // Nothing cached to undo; the scene is fetched again.
+    Refetch,
+}
+
+impl Change {
+    // The id something added goes by until it's saved.
+    pub fn unsaved_id(&self) -> Option<i32> {
+        match self {
+            Change::ProjectAdded { project }  => Some(project.id),
+            Change::WorkerAdded { worker }    => Some(worker.id),
+            Change::TaskAdded { task, .. }    => Some(task.id),
+            _                                 => None,
+        }
+    }
+
+    // What it takes to make it.
+    pub fn perm(&self) -> Perm {
+        match self {
+            Change::ProjectAdded { .. }   => Perm::CreateProject,
+            Change::WorkerAdded { .. }    => Perm::CreateWorker,
+            Change::TaskAdded { .. }      => Perm::CreateTask,
+            Change::TaskAssigned { .. }   => Perm::AssignTask,
+            Change::TaskUnassigned { .. } => Perm::AssignTask,
+            Change::RoleGranted { .. }    => Perm::ManageUsers,
+            Change::RoleRevoked { .. }    => Perm::ManageUsers,
+            Change::Created { .. }        => Perm::View,
+        }
+    }
+
+    // For telling the user which change didn't go through.
+    pub fn describe(&self) -> String {
+        match self {
+            Change::ProjectAdded { project }          => format!("adding project {}", project.name),
+            Change::WorkerAdded { worker }            => format!("adding worker {}", worker.name),
+            Change::TaskAdded { task, .. }            => format!("adding task {}", task.name),
+            Change::TaskAssigned { task_id, worker_id } => format!("assigning task {} to worker {}", task_id, worker_id),
+            Change::TaskUnassigned { task_id }        => format!("unassigning task {}", task_id),
+            Change::RoleGranted { user_id, role }     => format!("granting {} to {}", role.id, user_id),
+            Change::RoleRevoked { user_id, role_id }  => format!("revoking {} from {}", role_id, user_id),
+            Change::Created { unsaved, id }           => format!("saving {} as {}", unsaved, id),
+        }
+    }
+}
+
+impl Cache {
+    pub fn project(&self, id: ProjectId) -> Option<ProjectDetails> {
+        self.projects.get(&id).cloned()
+    }
+
+    pub fn worker(&self, id: WorkerId) -> Option<WorkerDetails> {
+        self.workers.get(&id).cloned()
+    }
+
+    pub fn task(&self, id: TaskId) -> Option<TaskDetails> {
+        self.tasks.get(&id).cloned()
+    }
+
+    // The version to write against: the last one seen, or 0 if it
+    // hasn't been, which the server will turn down if it's been written.
+    pub fn project_version(&self, id: ProjectId) -> Version {
+        self.projects.get(&id).map_or(0, |project| project.version)
+    }
+
+    pub fn worker_version(&self, id: WorkerId) -> Version {
+        self.workers.get(&id).map_or(0, |worker| worker.version)
+    }
+
+    pub fn task_version(&self, id: TaskId) -> Version {
+        self.task_versions.get(&id).copied().unwrap_or(0)
+    }
+
+    // Real ids are positive, so these can't clash with them.
+    pub fn unsaved_id(&mut self) -> i32 {
+        self.unsaved -= 1;
+        self.unsaved
+    }
+
+    // Skips the ids still waiting in the outbox from an earlier visit.
+    pub fn skip_unsaved(&mut self, lowest: i32) {
+        self.unsaved = self.unsaved.min(lowest);
+    }
+
+    // Keeps whatever the scene shows.
+    pub fn remember(&mut self, scene: &Scene) {
+        match scene {
+            Scene::Workers(view)        => self.put_worker_rows(&view.workers.items),
+            Scene::ProjectDetails(view) => self.put_project(view.project.clone()),
+            Scene::WorkerDetails(view)  => self.put_worker(view.worker.clone()),
+            Scene::MyTasks(view)        => self.put_worker(view.worker.clone()),
+            Scene::TaskDetails(view)    => self.put_task(view.task.clone()),
+            Scene::AssignTask(view)     => {
+                self.put_worker_rows(&view.workers);
+                self.put_task(view.task.clone());
+            }
+            Scene::Conflict(view)       => match &view.theirs {
+                Theirs::Task(task)       => self.put_task(task.clone()),
+                Theirs::Project(project) => self.put_project(project.clone()),
+            },
+            _                           => (),
+        }
+    }
+
+    // Brings the scene on screen up to date with the cache after `change`.
+    // Details come from the cache; lists of pages, which aren't cached,
+    // get the change made to them directly.
+    pub fn show(&self, scene: &mut Scene, change: &Change) {
+        if self.refresh(scene) {
+            return;
+        }
+        match (scene, change) {
+            (Scene::Projects(view), Change::ProjectAdded { project }) => {
+                view.projects.items.push(project.clone());
+                view.projects.total += 1;
+            }
+            (Scene::Workers(view), Change::WorkerAdded { worker }) => {
+                view.workers.items.push(worker.clone());
+                view.workers.total += 1;
+            }
+            (Scene::Users(view), Change::RoleGranted { user_id, role }) => {
+                let user = view.users.items.iter_mut().find(|user| &user.id == user_id);
+                if let Some(user) = user.filter(|user| user.roles.iter().all(|r| r.id != role.id)) {
+                    user.roles.push(role.clone());
+                }
+            }
+            (Scene::Projects(view), Change::Created { unsaved, id }) => {
+                let dropped = renumber(&mut view.projects.items, *unsaved, *id);
+                view.projects.total -= dropped;
+            }
+            (Scene::Workers(view), Change::Created { unsaved, id }) => {
+                let dropped = renumber(&mut view.workers.items, *unsaved, *id);
+                view.workers.total -= dropped;
+            }
+            (Scene::Users(view), Change::RoleRevoked { user_id, role_id }) => {
+                if let Some(user) = view.users.items.iter_mut().find(|user| &user.id == user_id) {
+                    user.roles.retain(|role| &role.id != role_id);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // Like `show`, for an event.
+    pub fn show_event(&self, scene: &mut Scene, event: &Event) {
+        if self.refresh(scene) {
+            return;
+        }
+        match (scene, event) {
+            (Scene::Projects(view), Event::Created(Entity::Project(project))) =>
+                put_row(&mut view.projects, project_row(project), true),
+            (Scene::Projects(view), Event::Updated(Entity::Project(project))) =>
+                put_row(&mut view.projects, project_row(project), false),
+            (Scene::Projects(view), Event::Deleted(EntityRef::Project(id))) =>
+                remove_row(&mut view.projects, *id),
+            (Scene::Workers(view), Event::Created(Entity::Worker(worker))) =>
+                put_row(&mut view.workers, worker_row(worker), true),
+            (Scene::Workers(view), Event::Updated(Entity::Worker(worker))) =>
+                put_row(&mut view.workers, worker_row(worker), false),
+            (Scene::Workers(view), Event::Deleted(EntityRef::Worker(id))) =>
+                remove_row(&mut view.workers, *id),
+            _ => (),
+        }
+    }
+
+    // Details scenes show what's cached. False for the rest.
+    fn refresh(&self, scene: &mut Scene) -> bool {
+        match scene {
+            Scene::ProjectDetails(view) => {
+                if let Some(project) = self.project(view.project.id) {
+                    view.project = project;
+                }
+            }
+            Scene::WorkerDetails(view) => {
+                if let Some(worker) = self.worker(view.worker.id) {
+                    view.worker = worker;
+                }
+            }
+            Scene::MyTasks(view) => {
+                if let Some(worker) = self.worker(view.worker.id) {
+                    view.worker = worker;
+                }
+            }
+            Scene::TaskDetails(view) => {
+                if let Some(task) = self.task(view.task.id) {
+                    view.task = task;
+                }
+            }
+            Scene::AssignTask(view) => {
+                if let Some(task) = self.task(view.task.id) {
+                    view.task = task;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // Takes in a change made elsewhere. Anything older than what's here
+    // is left out: it's the echo of a change the user has since made
+    // again.
+    pub fn receive(&mut self, event: &Event) {
+        match event {
+            Event::Created(entity) | Event::Updated(entity) => match entity {
+                Entity::Project(project) if project.version >= self.project_version(project.id) =>
+                    self.put_project(project.clone()),
+                Entity::Worker(worker) if worker.version >= self.worker_version(worker.id) =>
+                    self.put_worker(worker.clone()),
+                Entity::Task(task) if task.version >= self.task_version(task.id) =>
+                    self.put_task(task.clone()),
+                _ => (),
+            },
+            Event::Deleted(EntityRef::Project(id)) => {
+                self.projects.remove(id);
+            }
+            Event::Deleted(EntityRef::Worker(id)) => {
+                self.workers.remove(id);
+                self.worker_rows.remove(id);
+            }
+            Event::Deleted(EntityRef::Task(id)) => self.remove_task(*id),
+        }
+    }
+
+    pub fn put_project(&mut self, project: ProjectDetails) {
+        self.projects.insert(project.id, project);
+    }
+
+    pub fn put_worker(&mut self, worker: WorkerDetails) {
+        self.worker_rows.insert(worker.id, worker_row(&worker));
+        self.workers.insert(worker.id, worker);
+    }
+
+    fn put_worker_rows(&mut self, workers: &[ListWorker]) {
+        for worker in workers {
+            self.worker_rows.insert(worker.id, worker.clone());
+        }
+    }
+
+    pub fn put_task(&mut self, task: TaskDetails) {
+        let status = if task.worker.is_some() { TaskStatus::Assigned } else { TaskStatus::Unassigned };
+        let row = ListTask { id: task.id, name: task.name.clone(), status };
+        self.place(row, Some(task.project.id), task.worker.as_ref().map(|w| w.id));
+        self.task_versions.insert(task.id, task.version);
+        self.tasks.insert(task.id, task);
+    }
+
+    pub fn edits(&self) -> u32 {
+        self.edits
+    }
+
+    // Call before `apply`.
+    pub fn undo(&self, change: &Change) -> Undo {
+        match change {
+            Change::ProjectAdded { project }  => Undo::Remove { unsaved: project.id, project_id: None },
+            Change::WorkerAdded { worker }    => Undo::Remove { unsaved: worker.id, project_id: None },
+            Change::TaskAdded { project_id, task } =>
+                Undo::Remove { unsaved: task.id, project_id: Some(*project_id) },
+            Change::TaskAssigned { task_id, .. } | Change::TaskUnassigned { task_id } => {
+                match self.holder(*task_id) {
+                    Some(worker_id) => Undo::Reassign {
+                        task_id: *task_id,
+                        worker_id,
+                        details: self.task(*task_id),
+                    },
+                    None            => Undo::Forget { task_id: *task_id },
+                }
+            }
+            _ => Undo::Refetch,
+        }
+    }
+
+    pub fn revert(&mut self, undo: &Undo) {
+        self.edits += 1;
+        match undo {
+            Undo::Remove { unsaved, project_id } => {
+                self.remove_task(*unsaved);
+                if let Some(project) = project_id.and_then(|id| self.projects.get_mut(&id)) {
+                    project.version = project.version.saturating_sub(1);
+                }
+            }
+            Undo::Reassign { task_id, worker_id, details } => {
+                self.unbump(*task_id);
+                self.assign(*task_id, *worker_id);
+                if let Some(details) = details.as_ref().filter(|_| !self.tasks.contains_key(task_id)) {
+                    let version = self.task_version(*task_id);
+                    self.tasks.insert(*task_id, TaskDetails { version, ..details.clone() });
+                }
+            }
+            Undo::Forget { task_id } => {
+                self.unbump(*task_id);
+                self.tasks.remove(task_id);
+                for worker in self.workers.values_mut() {
+                    worker.tasks.retain(|task| task.id != *task_id);
+                }
+                let rows = self.projects.values_mut().flat_map(|project| &mut project.tasks);
+                for task in rows.filter(|task| task.id == *task_id) {
+                    task.status = TaskStatus::Assigned;
+                }
+            }
+            Undo::Refetch => (),
+        }
+    }
+
+    // Brings the scene on screen up to date with the cache after `undo`,
+    // the way `show` does. False if it has to be fetched again.
+    pub fn show_undo(&self, scene: &mut Scene, undo: &Undo) -> bool {
+        match (scene, undo) {
+            (_, Undo::Refetch) => false,
+            (Scene::TaskDetails(view), Undo::Forget { task_id }) if view.task.id == *task_id => false,
+            (Scene::AssignTask(view), Undo::Forget { task_id }) if view.task.id == *task_id => false,
+            (Scene::Projects(view), Undo::Remove { unsaved, .. }) => {
+                remove_row(&mut view.projects, *unsaved);
+                true
+            }
+            (Scene::Workers(view), Undo::Remove { unsaved, .. }) => {
+                remove_row(&mut view.workers, *unsaved);
+                true
+            }
+            (scene, _) => {
+                self.refresh(scene);
+                true
+            }
+        }
+    }
+
+    pub fn apply(&mut self, change: &Change) {
+        self.edits += 1;
+        match change {
+            // Written the way the server writes them, versions and all, so
+            // the next change isn't made against a stale one.
+            Change::TaskAdded { project_id, task } => {
+                self.place(task.clone(), Some(*project_id), None);
+                self.task_versions.insert(task.id, 0);
+                if let Some(project) = self.projects.get_mut(project_id) {
+                    project.version += 1;
+                }
+            }
+            Change::TaskAssigned { task_id, worker_id } => {
+                self.bump(*task_id);
+                self.assign(*task_id, Some(*worker_id));
+            }
+            Change::TaskUnassigned { task_id } => {
+                self.bump(*task_id);
+                self.assign(*task_id, None);
+            }
+            // A task may have been assigned, or fetched, before it was
+            // saved.
+            Change::Created { unsaved, id } => {
+                for project in self.projects.values_mut() {
+                    renumber(&mut project.tasks, *unsaved, *id);
+                }
+                for worker in self.workers.values_mut() {
+                    renumber(&mut worker.tasks, *unsaved, *id);
+                }
+                if let Some(task) = self.tasks.remove(unsaved) {
+                    self.tasks.entry(*id).or_insert(TaskDetails { id: *id, ..task });
+                }
+                if let Some(version) = self.task_versions.remove(unsaved) {
+                    self.task_versions.entry(*id).or_insert(version);
+                }
+            }
+            // Only in lists of pages, which aren't cached.
+            _                                           => (),
+        }
+    }
+
+    fn bump(&mut self, task_id: TaskId) {
+        if let Some(version) = self.task_versions.get_mut(&task_id) {
+            *version += 1;
+        }
+    }
+
+    fn unbump(&mut self, task_id: TaskId) {
+        if let Some(version) = self.task_versions.get_mut(&task_id) {
+            *version = version.saturating_sub(1);
+        }
+    }
+
+    // Who has the task, if anyone, as far as the cache knows. A task
+    // listed as unassigned has no one.
+    fn holder(&self, task_id: TaskId) -> Option<Option<WorkerId>> {
+        if let Some(task) = self.tasks.get(&task_id) {
+            return Some(task.worker.as_ref().map(|worker| worker.id));
+        }
+        if let Some(worker) = self.workers.values().find(|worker| worker.tasks.iter().any(|task| task.id == task_id)) {
+            return Some(Some(worker.id));
+        }
+        self.projects.values().flat_map(|project| &project.tasks)
+            .find(|task| task.id == task_id && task.status == TaskStatus::Unassigned)
+            .map(|_| None)
+    }
+
+    fn remove_task(&mut self, task_id: TaskId) {
+        self.tasks.remove(&task_id);
+        self.task_versions.remove(&task_id);
+        for project in self.projects.values_mut() {
+            project.tasks.retain(|task| task.id != task_id);
+        }
+        for worker in self.workers.values_mut() {
+            worker.tasks.retain(|task| task.id != task_id);
+        }
+    }
+
+    fn assign(&mut self, task_id: TaskId, worker_id: Option<WorkerId>) {
+        let status = if worker_id.is_some() { TaskStatus::Assigned } else { TaskStatus::Unassigned };
+        if let Some(name) = self.task_name(task_id) {
+            self.place(ListTask { id: task_id, name, status }, None, worker_id);
+        }
+
+        // The details name the worker. If we don't know the name, drop
+        // them so they're fetched.
+        let worker = match worker_id {
+            None     => None,
+            Some(id) => match self.worker_rows.get(&id) {
+                Some(worker) => Some(worker.clone()),
+                None => {
+                    self.tasks.remove(&task_id);
+                    return;
+                }
+            },
+        };
+        let version = self.task_version(task_id);
+        if let Some(task) = self.tasks.get_mut(&task_id) {
+            task.worker = worker;
+            task.version = version;
+        }
+    }
+
+    fn task_name(&self, task_id: TaskId) -> Option<TaskName> {
+        if let Some(task) = self.tasks.get(&task_id) {
+            return Some(task.name.clone());
+        }
+        self.projects.values().flat_map(|project| &project.tasks)
+            .chain(self.workers.values().flat_map(|worker| &worker.tasks))
+            .find(|task| task.id == task_id)
+            .map(|task| task.name.clone())
+    }
+
+    // Updates the task's row wherever it's listed, adds it to
+    // `project_id`'s list if it's missing there, and moves it to
+    // `worker_id`'s list.
+    fn place(&mut self, row: ListTask, project_id: Option<ProjectId>, worker_id: Option<WorkerId>) {
+        for project in self.projects.values_mut() {
+            let position = project.tasks.iter().position(|task| task.id == row.id);
+            match position {
+                Some(i)                                => project.tasks[i] = row.clone(),
+                None if Some(project.id) == project_id => project.tasks.push(row.clone()),
+                None                                   => (),
+            }
+        }
+
+        for worker in self.workers.values_mut() {
+            let position = worker.tasks.iter().position(|task| task.id == row.id);
+            match (position, Some(worker.id) == worker_id) {
+                (Some(i), true)  => worker.tasks[i] = row.clone(),
+                (Some(i), false) => { worker.tasks.remove(i); }
+                (None, true)     => worker.tasks.push(row.clone()),
+                (None, false)    => (),
+            }
+        }
+    }
+}
+
+// A row in a list, by id.
+trait Row {
+    fn id(&self) -> i32;
+    fn set_id(&mut self, id: i32);
+}
+
+impl Row for ListProject {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: i32) {
+        self.id = id;
+    }
+}
+
+impl Row for ListWorker {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: i32) {
+        self.id = id;
+    }
+}
+
+impl Row for ListTask {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    fn set_id(&mut self, id: i32) {
+        self.id = id;
+    }
+}
+
+fn project_row(project: &ProjectDetails) -> ListProject {
+    ListProject { id: project.id, name: project.name.clone(), task_count: project.tasks.len() }
+}
+
+fn worker_row(worker: &WorkerDetails) -> ListWorker {
+    ListWorker { id: worker.id, name: worker.name.clone(), task_count: worker.tasks.len() }
+}
+
+// Gives the row added as `unsaved` its real id, or drops it if an event
+// got a row with that id there first. Returns how many were dropped.
+fn renumber<T: Row>(rows: &mut Vec<T>, unsaved: i32, id: i32) -> usize {
+    if rows.iter().any(|row| row.id() == id) {
+        let before = rows.len();
+        rows.retain(|row| row.id() != unsaved);
+        return before - rows.len();
+    }
+    for row in rows.iter_mut().filter(|row| row.id() == unsaved) {
+        row.set_id(id);
+    }
+    0
+}
+
+// Replaces the row with the same id. New ones go at the end, if `add`;
+// an update to a row not shown is on another page.
+fn put_row<T: Row>(page: &mut Page<T>, row: T, add: bool) {
+    match page.items.iter().position(|item| item.id() == row.id()) {
+        Some(i) => page.items[i] = row,
+        None if add => {
+            page.items.push(row);
+            page.total += 1;
+        }
+        None => (),
+    }
+}
+
+fn remove_row<T: Row>(page: &mut Page<T>, id: i32) {
+    let before = page.items.len();
+    page.items.retain(|item| item.id() != id);
+    page.total -= before - page.items.len();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: TaskId, status: TaskStatus) -> ListTask {
+        ListTask { id, name: format!("Task {}", id), status }
+    }
+
+    fn worker(id: WorkerId, tasks: Vec<ListTask>) -> WorkerDetails {
+        WorkerDetails { id, name: format!("Worker {}", id), tasks, version: 1 }
+    }
+
+    // Task 1 is with worker 5; worker 6 has nothing.
+    fn cache() -> Cache {
+        let mut cache = Cache::default();
+        cache.put_project(ProjectDetails {
+            id: 3,
+            name: "Roof".into(),
+            tasks: vec![row(1, TaskStatus::Assigned), row(2, TaskStatus::Unassigned)],
+            version: 1,
+        });
+        cache.put_worker(worker(5, vec![row(1, TaskStatus::Assigned)]));
+        cache.put_worker(worker(6, vec![]));
+        cache.put_task(TaskDetails {
+            id: 1,
+            name: "Task 1".into(),
+            project: ListProject { id: 3, name: "Roof".into(), task_count: 2 },
+            worker: Some(ListWorker { id: 5, name: "Worker 5".into(), task_count: 1 }),
+            version: 1,
+        });
+        cache
+    }
+
+    #[test]
+    fn assigning_moves_the_task_everywhere() {
+        let mut cache = cache();
+        cache.apply(&Change::TaskAssigned { task_id: 2, worker_id: 6 });
+        cache.apply(&Change::TaskAssigned { task_id: 1, worker_id: 6 });
+
+        assert_eq!(cache.project(3).unwrap().tasks, vec![row(1, TaskStatus::Assigned), row(2, TaskStatus::Assigned)]);
+        assert_eq!(cache.worker(5).unwrap().tasks, vec![]);
+        assert_eq!(cache.worker(6).unwrap().tasks, vec![row(2, TaskStatus::Assigned), row(1, TaskStatus::Assigned)]);
+        assert_eq!(cache.task(1).unwrap().worker.unwrap().name, "Worker 6");
+    }
+
+    #[test]
+    fn unassigning_clears_the_worker() {
+        let mut cache = cache();
+        cache.apply(&Change::TaskUnassigned { task_id: 1 });
+
+        assert_eq!(cache.project(3).unwrap().tasks[0], row(1, TaskStatus::Unassigned));
+        assert_eq!(cache.worker(5).unwrap().tasks, vec![]);
+        assert_eq!(cache.task(1).unwrap().worker, None);
+    }
+
+    #[test]
+    fn assigning_to_an_unknown_worker_drops_the_details() {
+        let mut cache = cache();
+        cache.apply(&Change::TaskAssigned { task_id: 1, worker_id: 9 });
+
+        assert!(cache.task(1).is_none());
+        assert_eq!(cache.worker(5).unwrap().tasks, vec![]);
+        // Still writable.
+        assert_eq!(cache.task_version(1), 2);
+    }
+
+    #[test]
+    fn added_tasks_join_their_project() {
+        let mut cache = cache();
+        cache.apply(&Change::TaskAdded { project_id: 3, task: row(7, TaskStatus::Unassigned) });
+
+        assert_eq!(cache.project(3).unwrap().tasks.last(), Some(&row(7, TaskStatus::Unassigned)));
+    }
+
+    #[test]
+    fn fresh_task_details_update_the_lists() {
+        let mut cache = cache();
+        cache.put_task(TaskDetails {
+            id: 2,
+            name: "Gutters".into(),
+            project: ListProject { id: 3, name: "Roof".into(), task_count: 2 },
+            worker: Some(ListWorker { id: 6, name: "Worker 6".into(), task_count: 1 }),
+            version: 1,
+        });
+
+        let expected = ListTask { id: 2, name: "Gutters".into(), status: TaskStatus::Assigned };
+        assert_eq!(cache.project(3).unwrap().tasks[1], expected);
+        assert_eq!(cache.worker(6).unwrap().tasks, vec![expected]);
+    }
+
+    #[test]
+    fn workers_from_the_picker_are_known_by_name() {
+        let mut cache = cache();
+        cache.remember(&Scene::AssignTask(AssignTaskView {
+            task: cache.task(1).unwrap(),
+            workers: vec![ListWorker { id: 9, name: "Bo".into(), task_count: 0 }],
+            perms: Perms::default(),
+        }));
+        cache.apply(&Change::TaskAssigned { task_id: 1, worker_id: 9 });
+
+        assert_eq!(cache.task(1).unwrap().worker.unwrap().name, "Bo");
+    }
+
+    #[test]
+    fn reverting_takes_back_only_that_change() {
+        let mut cache = cache();
+        let assigned = Change::TaskAssigned { task_id: 1, worker_id: 6 };
+        let undo = cache.undo(&assigned);
+        cache.apply(&assigned);
+        cache.apply(&Change::TaskAdded { project_id: 3, task: row(-1, TaskStatus::Unassigned) });
+        cache.revert(&undo);
+
+        assert_eq!(cache.worker(5).unwrap().tasks, vec![row(1, TaskStatus::Assigned)]);
+        assert_eq!(cache.worker(6).unwrap().tasks, vec![]);
+        assert_eq!(cache.task(1).unwrap().worker.unwrap().id, 5);
+        assert_eq!(cache.task_version(1), 1);
+        assert_eq!(cache.project(3).unwrap().tasks.last(), Some(&row(-1, TaskStatus::Unassigned)));
+    }
+
+    #[test]
+    fn unsaved_ids_never_repeat() {
+        let mut cache = Cache::default();
+        let first = cache.unsaved_id();
+        assert!(first < 0);
+        assert!(cache.unsaved_id() < first);
+    }
+
+    #[test]
+    fn events_update_every_list_but_not_with_older_news() {
+        let mut cache = cache();
+        let moved = TaskDetails {
+            worker: Some(ListWorker { id: 6, name: "Worker 6".into(), task_count: 1 }),
+            version: 2,
+            ..cache.task(1).unwrap()
+        };
+        cache.receive(&Event::Updated(Entity::Task(moved.clone())));
+        assert_eq!(cache.worker(6).unwrap().tasks, vec![row(1, TaskStatus::Assigned)]);
+        assert_eq!(cache.worker(5).unwrap().tasks, vec![]);
+
+        cache.receive(&Event::Updated(Entity::Task(TaskDetails { worker: None, version: 1, ..moved })));
+        assert_eq!(cache.task(1).unwrap().worker.unwrap().id, 6);
+
+        cache.receive(&Event::Deleted(EntityRef::Task(1)));
+        assert!(cache.task(1).is_none());
+        assert_eq!(cache.project(3).unwrap().tasks, vec![row(2, TaskStatus::Unassigned)]);
+        assert_eq!(cache.worker(6).unwrap().tasks, vec![]);
+    }
+
+    #[test]
+    fn older_worker_events_are_left_out() {
+        let mut cache = cache();
+        let renamed = WorkerDetails { name: "Ann".into(), version: 2, ..worker(6, vec![]) };
+        cache.receive(&Event::Updated(Entity::Worker(renamed.clone())));
+        cache.receive(&Event::Updated(Entity::Worker(WorkerDetails { version: 1, ..worker(6, vec![]) })));
+
+        assert_eq!(cache.worker(6), Some(renamed));
+    }
+}
@@ -0,0 +1,158 @@
+// Where the API is and how to log in, per environment. The JSON files in
+// config/ are compiled in and one is picked with APP_ENV when building,
+// e.g. `APP_ENV=production cargo-web deploy`; the default is development.
+
+use serde::{ Serialize, Deserialize };
+use crate::localauth::LocalConfig;
+use crate::contract::EVENTS_PATH;
+
+const ENVIRONMENTS: &[(&str, &str)] = &[
+    ("development", include_str!("../config/development.json")),
+    ("mock",        include_str!("../config/mock.json")),
+    ("production",  include_str!("../config/production.json")),
+];
+
+#[derive(Deserialize,Debug,Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    // Requests go to "{api_url}/api/{action}".
+    pub api_url: String,
+    pub auth: AuthConfig,
+    // How to hear about changes made by others. Off if left out.
+    #[serde(default)]
+    pub live: LiveConfig,
+}
+
+#[derive(Deserialize,Debug,Clone,PartialEq,Default)]
+#[serde(tag = "via", rename_all = "lowercase")]
+pub enum LiveConfig {
+    #[default]
+    Off,
+    // The event stream next to the API (see Config::events_url).
+    WebSocket,
+    // Fetching the scene again this often, for servers without one.
+    Poll { every_secs: u32 },
+}
+
+#[derive(Deserialize,Debug,Clone)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum AuthConfig {
+    Auth0(Auth0Config),
+    Local(LocalConfig),
+}
+
+// Handed to createAuth0Client as is.
+#[derive(Serialize,Deserialize,Debug,Clone)]
+pub struct Auth0Config {
+    pub domain: String,
+    pub client_id: String,
+    // Seconds of clock skew to allow when checking tokens.
+    #[serde(default)]
+    pub leeway: u32,
+}
+
+impl Config {
+    // The config for the environment this was built for.
+    pub fn embedded() -> Result<Self, String> {
+        let env = option_env!("APP_ENV").unwrap_or("development");
+        let (_, text) = ENVIRONMENTS.iter()
+            .find(|(name, _)| *name == env)
+            .ok_or_else(|| format!("APP_ENV {:?} has no config/{}.json", env, env))?;
+        Self::parse(text).map_err(|e| format!("config/{}.json: {}", env, e))
+    }
+
+    // The API's event stream, over ws:// or wss:// to match api_url.
+    pub fn events_url(&self) -> String {
+        // "://host" or "s://host"; validate makes sure it starts with http.
+        let rest = self.api_url.strip_prefix("http").unwrap_or(&self.api_url);
+        format!("ws{}/api/{}", rest, EVENTS_PATH)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if !self.api_url.starts_with("http://") && !self.api_url.starts_with("https://") {
+            return Err(format!("api_url {:?} is not an http(s) URL", self.api_url));
+        }
+        if self.api_url.ends_with('/') {
+            return Err(format!("api_url {:?} should not end with '/'", self.api_url));
+        }
+
+        if self.live == (LiveConfig::Poll { every_secs: 0 }) {
+            return Err("live polling needs every_secs of at least 1".into());
+        }
+
+        match &self.auth {
+            AuthConfig::Auth0(auth0) => {
+                if auth0.domain.is_empty() || auth0.client_id.is_empty() {
+                    return Err("auth0 needs a domain and a client_id".into());
+                }
+            }
+            AuthConfig::Local(local) => {
+                if local.accounts.is_empty() {
+                    return Err("the local provider needs at least one account".into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_environment_is_valid() {
+        for (name, text) in ENVIRONMENTS {
+            if let Err(e) = Config::parse(text) {
+                panic!("config/{}.json: {}", name, e);
+            }
+        }
+    }
+
+    #[test]
+    fn bad_configs_say_what_is_wrong() {
+        let error = |text| Config::parse(text).unwrap_err();
+
+        assert_eq!(
+            error(r#"{ "api_url": "localhost:8001", "auth": { "provider": "local", "accounts": ["a"] } }"#),
+            r#"api_url "localhost:8001" is not an http(s) URL"#
+            );
+        assert_eq!(
+            error(r#"{ "api_url": "http://x/", "auth": { "provider": "local", "accounts": ["a"] } }"#),
+            r#"api_url "http://x/" should not end with '/'"#
+            );
+        assert_eq!(
+            error(r#"{ "api_url": "http://x", "auth": { "provider": "local", "accounts": [] } }"#),
+            "the local provider needs at least one account"
+            );
+        assert!(error(r#"{ "api_url": "http://x", "auth": { "provider": "okta" } }"#)
+            .starts_with("unknown variant `okta`"));
+        assert!(error(r#"{ "api_url": "http://x", "auth": { "provider": "local", "accounts": ["a"] }, "debug": true }"#)
+            .starts_with("unknown field `debug`"));
+        assert!(error(r#"{ "api_url": "http://x", "auth": { "provider": "local", "accounts": ["a"] }, "live": { "via": "carrier pigeon" } }"#)
+            .starts_with("unknown variant `carrier pigeon`"));
+        assert_eq!(
+            error(r#"{ "api_url": "http://x", "auth": { "provider": "local", "accounts": ["a"] }, "live": { "via": "poll", "every_secs": 0 } }"#),
+            "live polling needs every_secs of at least 1"
+            );
+    }
+
+    #[test]
+    fn events_are_next_to_the_api() {
+        let config = |api_url| Config::parse(&format!(
+            r#"{{ "api_url": "{}", "auth": {{ "provider": "local", "accounts": ["a"] }}, "live": {{ "via": "websocket" }} }}"#,
+            api_url
+            )).unwrap();
+
+        assert_eq!(config("http://localhost:8001").events_url(), "ws://localhost:8001/api/events");
+        assert_eq!(config("https://api.example.com").events_url(), "wss://api.example.com/api/events");
+        assert_eq!(config("http://x").live, LiveConfig::WebSocket);
+    }
+}
@@ -0,0 +1,291 @@
+// Every call the frontend makes to the API: its method, path, what it
+// sends and what it gets back. msg.rs builds requests from these and the
+// mock backend is tested against them, so the three can't drift apart.
+// `openapi()` publishes the same list for the backend.
+//
+// Paged lists, the paths ending in "{offset}/{limit}", also take the
+// list's filter and sort as a query, e.g. "?q=mob&sort=tasks&desc" (see
+// ListState::search_query). They're applied before paging, so `total`
+// counts what matched.
+
+use std::fmt::Display;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{ json, Map, Value };
+use crate::domain::*;
+use crate::schema::*;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Method {
+    Get,
+    Post,
+}
+
+impl Method {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get  => "get",
+            Method::Post => "post",
+        }
+    }
+}
+
+pub trait Endpoint {
+    const METHOD: Method;
+    // Relative to "/api/". "{name}" marks a path parameter; they are all
+    // integers.
+    const PATH: &'static str;
+    // The JSON body. GET calls send none and use ().
+    type Params: Serialize + Schema;
+    // What comes back inside the Ok of the `Result<_, String>` envelope.
+    type Response: DeserializeOwned + Schema;
+
+    // PATH with its parameters filled in, in order.
+    fn action(args: &[&dyn Display]) -> String {
+        let mut args = args.iter();
+        let mut action = String::new();
+        let mut rest = Self::PATH;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').expect("unclosed path parameter") + start;
+            let arg = args.next().expect("too few path arguments");
+            action.push_str(&rest[..start]);
+            action.push_str(&arg.to_string());
+            rest = &rest[end + 1..];
+        }
+        assert!(args.next().is_none(), "too many path arguments");
+        action.push_str(rest);
+        action
+    }
+}
+
+// An endpoint with its types erased, for listing them all.
+pub struct Description {
+    pub name: &'static str,
+    pub method: Method,
+    pub path: &'static str,
+    pub params: fn(&mut Definitions) -> Value,
+    pub response: fn(&mut Definitions) -> Value,
+}
+
+impl Description {
+    fn of<E: Endpoint>(name: &'static str) -> Self {
+        Self {
+            name,
+            method: E::METHOD,
+            path: E::PATH,
+            params: E::Params::schema,
+            response: E::Response::schema,
+        }
+    }
+
+    pub fn path_params(&self) -> Vec<&'static str> {
+        self.path
+            .split('/')
+            .filter_map(|s| s.strip_prefix('{'))
+            .filter_map(|s| s.strip_suffix('}'))
+            .collect()
+    }
+
+    pub fn query_params(&self) -> &'static [&'static str] {
+        if self.path.ends_with("{offset}/{limit}") {
+            &["q", "sort", "desc"]
+        }
+        else {
+            &[]
+        }
+    }
+}
+
+macro_rules! endpoints {
+    ($($name: ident: $method: ident $path: literal, $params: ty => $response: ty;)+) => {
+        $(
+            pub struct $name;
+
+            impl Endpoint for $name {
+                const METHOD: Method = Method::$method;
+                const PATH: &'static str = $path;
+                type Params = $params;
+                type Response = $response;
+            }
+        )+
+
+        pub fn endpoints() -> Vec<Description> {
+            vec![$(Description::of::<$name>(stringify!($name)),)+]
+        }
+    }
+}
+
+endpoints! {
+    GetProjects:     Get  "get_projects",                       ()                 => Vec<ListProject>;
+    GetProjectsPage: Get  "get_projects_page/{offset}/{limit}", ()                 => Page<ListProject>;
+    GetWorkers:      Get  "get_workers",                        ()                 => Vec<ListWorker>;
+    GetWorkersPage:  Get  "get_workers_page/{offset}/{limit}",  ()                 => Page<ListWorker>;
+    GetUsers:        Get  "get_users",                          ()                 => Vec<User>;
+    GetUsersPage:    Get  "get_users_page/{offset}/{limit}",    ()                 => Page<User>;
+    GetMe:           Get  "get_me",                             ()                 => Option<User>;
+    GetRoles:        Get  "get_roles",                          ()                 => Vec<Role>;
+    GetProject:      Get  "get_project/{id}",                   ()                 => Option<ProjectDetails>;
+    GetWorker:       Get  "get_worker/{id}",                    ()                 => Option<WorkerDetails>;
+    GetTask:         Get  "get_task/{id}",                      ()                 => Option<TaskDetails>;
+    AddProject:      Post "add_project",                        AddProjectParams   => ProjectId;
+    AddWorker:       Post "add_worker",                         AddWorkerParams    => WorkerId;
+    AddTask:         Post "add_task",                           AddTaskParams      => TaskId;
+    AssignTask:      Post "assign_task",                        AssignTaskParams   => ();
+    UnassignTask:    Post "unassign_task",                      UnassignTaskParams => ();
+    GrantRole:       Post "grant_role",                         GrantRoleParams    => ();
+    RevokeRole:      Post "revoke_role",                        RevokeRoleParams   => ();
+    LinkWorker:      Post "link_worker",                        LinkWorkerParams   => ();
+}
+
+// Not a call: a WebSocket at "/api/events" that sends each Event as a
+// JSON text message. Nothing is sent the other way.
+pub const EVENTS_PATH: &str = "events";
+
+// An OpenAPI 3.1 document for every endpoint. Schemas for the domain
+// types are shared under components/schemas.
+pub fn openapi() -> Value {
+    let mut defs = Definitions::new();
+    let mut paths = Map::new();
+
+    for endpoint in endpoints() {
+        let mut parameters: Vec<Value> = endpoint.path_params()
+            .into_iter()
+            .map(|name| json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "integer" },
+            }))
+            .collect();
+        parameters.extend(endpoint.query_params().iter().map(|&name| json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "schema": query_schema(name),
+        })));
+
+        let response = (endpoint.response)(&mut defs);
+        let mut operation = json!({
+            "operationId": endpoint.name,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "The result, or an error message.",
+                    "content": {
+                        "application/json": { "schema": envelope(response) },
+                    },
+                },
+            },
+        });
+
+        if endpoint.method == Method::Post {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": {
+                    "application/json": { "schema": (endpoint.params)(&mut defs) },
+                },
+            });
+            // See Version.
+            operation["responses"]["409"] = json!({
+                "description": "The params name an older version than the server has. Nothing was changed.",
+            });
+        }
+
+        let mut item = Map::new();
+        item.insert(endpoint.method.as_str().into(), operation);
+        paths.insert(format!("/api/{}", endpoint.path), Value::Object(item));
+    }
+
+    paths.insert(format!("/api/{}", EVENTS_PATH), json!({
+        "get": {
+            "operationId": "Events",
+            "responses": {
+                "101": {
+                    "description": "Switches to a WebSocket that sends an Event as JSON whenever anything changes.",
+                    "content": {
+                        "application/json": { "schema": Event::schema(&mut defs) },
+                    },
+                },
+            },
+        },
+    }));
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "586 API",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": defs,
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+            },
+        },
+        "security": [{ "bearer": [] }],
+    })
+}
+
+fn query_schema(name: &str) -> Value {
+    match name {
+        "sort" => json!({ "type": "string", "enum": ["name", "id", "tasks", "status"] }),
+        // "desc" only has to be there; its value is ignored.
+        _      => json!({ "type": "string" }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_fills_path_parameters() {
+        assert_eq!(GetProjectsPage::action(&[&100, &50]), "get_projects_page/100/50");
+        assert_eq!(GetTask::action(&[&7]), "get_task/7");
+        assert_eq!(AddTask::action(&[]), "add_task");
+    }
+
+    #[test]
+    #[should_panic(expected = "too few path arguments")]
+    fn action_checks_argument_count() {
+        GetProject::action(&[]);
+    }
+
+    #[test]
+    fn openapi_lists_every_endpoint() {
+        let doc = openapi();
+        for endpoint in endpoints() {
+            let path = format!("/api/{}", endpoint.path);
+            let operation = &doc["paths"][&path][endpoint.method.as_str()];
+            assert_eq!(operation["operationId"], endpoint.name, "{}", path);
+            assert_eq!(
+                operation["parameters"].as_array().unwrap().len(),
+                endpoint.path_params().len() + endpoint.query_params().len(),
+                "{}", path
+                );
+            assert_eq!(operation["requestBody"].is_null(), endpoint.method == Method::Get, "{}", path);
+        }
+    }
+
+    #[test]
+    fn schemas_mark_optional_fields() {
+        let doc = openapi();
+        let schemas = &doc["components"]["schemas"];
+        assert_eq!(schemas["TaskDetails"]["required"], json!(["id", "name", "project"]));
+        assert_eq!(schemas["ListTask"]["required"], json!(["id", "name"]));
+        assert_eq!(
+            schemas["TaskDetails"]["properties"]["worker"]["oneOf"][0]["$ref"],
+            "#/components/schemas/ListWorker"
+            );
+    }
+
+    #[test]
+    fn openapi_describes_the_event_stream() {
+        let doc = openapi();
+        let events = &doc["paths"]["/api/events"]["get"]["responses"]["101"];
+        assert_eq!(events["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/Event");
+        let variants = &doc["components"]["schemas"]["Event"]["oneOf"];
+        assert_eq!(variants[2]["required"], json!(["Deleted"]));
+    }
+}
@@ -1,6 +1,10 @@
 #![allow(unused_imports)]
 
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
 use serde::{ Serialize, Deserialize };
+use serde_json::{ json, Value };
+use crate::schema::*;
 
 pub type ProjectName = String;
 pub type ProjectId = i32;
@@ -8,54 +12,251 @@ pub type TaskName = String;
 pub type TaskId = i32;
 pub type WorkerName = String;
 pub type WorkerId = i32;
+pub type UserId = String;
+pub type RoleId = String;
+// Bumped by the server on every write to a project, worker or task.
+// Writes carry the version they were made against and are turned down
+// if it's no longer current.
+pub type Version = u64;
 
-//#[derive(Serialize,Deserialize,Debug)]
-//pub enum Perm {
-//};
+// Rows requested per page by the paged list calls.
+pub const PAGE_SIZE: usize = 50;
 
+// What a role lets its users do. On the wire each is an integer; codes
+// this build doesn't know are kept as Unknown and grant nothing.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Perm {
+    View,
+    CreateProject,
+    AssignTask,
+    ManageUsers,
+    CreateWorker,
+    CreateTask,
+    Unknown(i32),
+}
+
+impl Perm {
+    pub const ALL: [Perm; 6] = [
+        Perm::View,
+        Perm::CreateProject,
+        Perm::AssignTask,
+        Perm::ManageUsers,
+        Perm::CreateWorker,
+        Perm::CreateTask,
+    ];
+
+    pub fn code(self) -> i32 {
+        match self {
+            Perm::View          => 0,
+            Perm::CreateProject => 1,
+            Perm::AssignTask    => 2,
+            Perm::ManageUsers   => 3,
+            Perm::CreateWorker  => 4,
+            Perm::CreateTask    => 5,
+            Perm::Unknown(code) => code,
+        }
+    }
+
+    pub fn from_code(code: i32) -> Perm {
+        Perm::ALL.iter()
+            .copied()
+            .find(|perm| perm.code() == code)
+            .unwrap_or(Perm::Unknown(code))
+    }
+}
+
+impl std::fmt::Display for Perm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Perm::View          => write!(f, "view"),
+            Perm::CreateProject => write!(f, "create project"),
+            Perm::AssignTask    => write!(f, "assign task"),
+            Perm::ManageUsers   => write!(f, "manage users"),
+            Perm::CreateWorker  => write!(f, "create worker"),
+            Perm::CreateTask    => write!(f, "create task"),
+            Perm::Unknown(code) => write!(f, "unknown ({})", code),
+        }
+    }
+}
+
+impl Serialize for Perm {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Perm {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        i32::deserialize(deserializer).map(Perm::from_code)
+    }
+}
+
+impl Schema for Perm {
+    fn schema(_: &mut Definitions) -> Value {
+        let known: Vec<String> = Perm::ALL.iter()
+            .map(|perm| format!("{} {}", perm.code(), perm))
+            .collect();
+        json!({
+            "type": "integer",
+            "format": "int32",
+            "description": format!("Permission code: {}.", known.join(", ")),
+        })
+    }
+}
+
+// Everything a user may do: the union of their roles' perms.
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct Perms(BTreeSet<Perm>);
+
+impl Perms {
+    // What anyone may do without logging in: read projects, workers and
+    // tasks.
+    pub fn guest() -> Self {
+        vec![Perm::View].into_iter().collect()
+    }
+
+    pub fn has(&self, perm: Perm) -> bool {
+        self.0.contains(&perm)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Perm> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl FromIterator<Perm> for Perms {
+    fn from_iter<I: IntoIterator<Item = Perm>>(perms: I) -> Self {
+        Perms(perms.into_iter()
+            .filter(|perm| !matches!(perm, Perm::Unknown(_)))
+            .collect())
+    }
+}
+
+impl<'a> FromIterator<&'a Role> for Perms {
+    fn from_iter<I: IntoIterator<Item = &'a Role>>(roles: I) -> Self {
+        roles.into_iter()
+            .flat_map(|role| role.perms.iter().copied())
+            .collect()
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Default)]
+pub enum TaskStatus {
+    #[default]
+    Unassigned,
+    Assigned,
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TaskStatus::Unassigned => write!(f, "unassigned"),
+            TaskStatus::Assigned   => write!(f, "assigned"),
+        }
+    }
+}
+
+impl Schema for TaskStatus {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "string", "enum": ["Unassigned", "Assigned"] })
+    }
+}
+
+// Whether a field's attributes give it a serde default. Doc comments and
+// other attributes are skipped, whatever they say; each `serde(...)` is
+// gone through an item at a time.
+macro_rules! serde_default {
+    () => { false };
+    (#[serde($($items: tt)*)] $($rest: tt)*) => { serde_default!(@items [$($items)*] $($rest)*) };
+    (#[$($other: tt)*] $($rest: tt)*) => { serde_default!($($rest)*) };
+    (@items [] $($rest: tt)*) => { serde_default!($($rest)*) };
+    (@items [default $(= $path: literal)? $(, $($more: tt)*)?] $($rest: tt)*) => { true };
+    (@items [$key: ident $(= $value: literal)? $(($($args: tt)*))? $(, $($more: tt)*)?] $($rest: tt)*) => {
+        serde_default!(@items [$($($more)*)?] $($rest)*)
+    };
+}
+
+// Also describes each struct for the API contract. A field is required
+// unless it is an Option or has a serde default (see `serde_default!`).
+// Attributes are taken as tokens rather than `meta`, which later macros
+// can't look inside.
 macro_rules! domain_derive {
-    ($($i: item)+) => {
-        $(#[derive(Serialize,Deserialize,Debug,Clone)]
-        $i)+
+    ($(
+        pub struct $name: ident {
+            $($(#[$($meta: tt)*])* pub $field: ident : $ty: ty,)*
+        }
+    )+) => {
+        $(
+            #[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+            pub struct $name {
+                $($(#[$($meta)*])* pub $field: $ty,)*
+            }
+
+            impl Schema for $name {
+                fn schema(defs: &mut Definitions) -> Value {
+                    define_object(defs, stringify!($name), |defs| vec![
+                        $((
+                            stringify!($field),
+                            <$ty as Schema>::schema(defs),
+                            !<$ty as Schema>::optional()
+                                && !serde_default!($(#[$($meta)*])*),
+                        ),)*
+                    ])
+                }
+            }
+        )+
     }
 }
 
 domain_derive! {
     pub struct Role {
-        pub id: String,
-        pub perms: Vec<i32>,
+        pub id: RoleId,
+        pub perms: Vec<Perm>,
     }
     
     pub struct User {
-        pub id: String,
+        pub id: UserId,
         pub roles: Vec<Role>,
+        // The worker this user does tasks as, if any.
+        #[serde(default)]
+        pub worker_id: Option<WorkerId>,
     }
 
     pub struct ListTask {
         pub id: TaskId,
         pub name: TaskName,
+        #[serde(default)]
+        pub status: TaskStatus,
     }
 
     pub struct ListProject {
         pub id: ProjectId,
         pub name: ProjectName,
+        #[serde(default)]
+        pub task_count: usize,
     }
     
     pub struct ListWorker {
         pub id: WorkerId,
         pub name: WorkerName,
+        #[serde(default)]
+        pub task_count: usize,
     }
 
     pub struct ProjectDetails {
         pub id: ProjectId,
         pub name: ProjectName,
         pub tasks: Vec<ListTask>,
+        #[serde(default)]
+        pub version: Version,
     }
     
     pub struct WorkerDetails {
         pub id: WorkerId,
         pub name: WorkerName,
         pub tasks: Vec<ListTask>,
+        #[serde(default)]
+        pub version: Version,
     }
     
     pub struct TaskDetails {
@@ -63,6 +264,8 @@ domain_derive! {
         pub name: TaskName,
         pub project: ListProject,
         pub worker: Option<ListWorker>,
+        #[serde(default)]
+        pub version: Version,
     }
     
     pub struct AddProjectParams {
@@ -73,17 +276,355 @@ domain_derive! {
         pub name: WorkerName,
     }
     
+    // `version` is the project's.
     pub struct AddTaskParams {
         pub name: TaskName,
         pub project_id: ProjectId,
+        pub version: Version,
     }
     
+    // `version` is the task's, here and below.
     pub struct AssignTaskParams {
         pub task_id: TaskId,
         pub worker_id: WorkerId,
+        pub version: Version,
     }
     
     pub struct UnassignTaskParams {
         pub task_id: TaskId,
+        pub version: Version,
+    }
+
+    pub struct GrantRoleParams {
+        pub user_id: UserId,
+        pub role_id: RoleId,
+    }
+
+    pub struct RevokeRoleParams {
+        pub user_id: UserId,
+        pub role_id: RoleId,
+    }
+
+    // A None worker_id unlinks.
+    pub struct LinkWorkerParams {
+        pub user_id: UserId,
+        pub worker_id: Option<WorkerId>,
+    }
+}
+
+// One page of a paged list call. Kept out of `domain_derive!` for being
+// generic; its schema is inlined rather than named.
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub offset: usize,
+    pub total: usize,
+}
+
+impl<T: Schema> Schema for Page<T> {
+    fn schema(defs: &mut Definitions) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "items": Vec::<T>::schema(defs),
+                "offset": usize::schema(defs),
+                "total": usize::schema(defs),
+            },
+            "required": ["items", "offset", "total"],
+        })
+    }
+}
+
+// Sent down the event stream (see contract::EVENTS_PATH) whenever anyone
+// changes something, the user included. Created and Updated carry the
+// whole thing as it is after the change.
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub enum Event {
+    Created(Entity),
+    Updated(Entity),
+    Deleted(EntityRef),
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub enum Entity {
+    Project(ProjectDetails),
+    Worker(WorkerDetails),
+    Task(TaskDetails),
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub enum EntityRef {
+    Project(ProjectId),
+    Worker(WorkerId),
+    Task(TaskId),
+}
+
+impl Schema for Event {
+    fn schema(defs: &mut Definitions) -> Value {
+        define_enum(defs, "Event", |defs| vec![
+            ("Created", Entity::schema(defs)),
+            ("Updated", Entity::schema(defs)),
+            ("Deleted", EntityRef::schema(defs)),
+        ])
+    }
+}
+
+impl Schema for Entity {
+    fn schema(defs: &mut Definitions) -> Value {
+        define_enum(defs, "Entity", |defs| vec![
+            ("Project", ProjectDetails::schema(defs)),
+            ("Worker", WorkerDetails::schema(defs)),
+            ("Task", TaskDetails::schema(defs)),
+        ])
+    }
+}
+
+impl Schema for EntityRef {
+    fn schema(defs: &mut Definitions) -> Value {
+        define_enum(defs, "EntityRef", |defs| vec![
+            ("Project", ProjectId::schema(defs)),
+            ("Worker", WorkerId::schema(defs)),
+            ("Task", TaskId::schema(defs)),
+        ])
+    }
+}
+
+impl Entity {
+    pub fn id(&self) -> EntityRef {
+        match self {
+            Entity::Project(project) => EntityRef::Project(project.id),
+            Entity::Worker(worker)   => EntityRef::Worker(worker.id),
+            Entity::Task(task)       => EntityRef::Task(task.id),
+        }
+    }
+}
+
+impl Event {
+    pub fn id(&self) -> EntityRef {
+        match self {
+            Event::Created(entity) | Event::Updated(entity) => entity.id(),
+            Event::Deleted(id)                              => *id,
+        }
+    }
+}
+
+impl EntityRef {
+    // Where its details are shown.
+    pub fn hash_path(&self) -> String {
+        match self {
+            EntityRef::Project(id) => format!("project/{}", id),
+            EntityRef::Worker(id)  => format!("worker/{}", id),
+            EntityRef::Task(id)    => format!("task/{}", id),
+        }
+    }
+}
+
+impl User {
+    pub fn perms(&self) -> Perms {
+        self.roles.iter().collect()
+    }
+
+    pub fn has_role(&self, id: &str) -> bool {
+        self.roles.iter().any(|role| role.id == id)
+    }
+}
+
+impl<T> Page<T> {
+    pub fn index(&self) -> usize {
+        self.offset / PAGE_SIZE
+    }
+
+    pub fn count(&self) -> usize {
+        self.total.div_ceil(PAGE_SIZE).max(1)
+    }
+}
+
+// The wire format, pinned down by the JSON in fixtures/golden. A renamed
+// field or a changed encoding fails here rather than against the server.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+
+    macro_rules! golden(
+        ($file: literal) => {
+            include_str!(concat!("../fixtures/golden/", $file, ".json"))
+        };
+    );
+
+    // `value` encodes to exactly `golden`, and decodes from it back to
+    // the same encoding.
+    fn assert_golden<T: Serialize + DeserializeOwned>(value: &T, golden: &str) {
+        let encoded = serde_json::to_string_pretty(value).unwrap();
+        assert_eq!(encoded, golden.trim_end());
+        let decoded: T = serde_json::from_str(golden).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&decoded).unwrap(), encoded);
+    }
+
+    fn list_project() -> ListProject {
+        ListProject { id: 2, name: "Roof".into(), task_count: 3 }
+    }
+
+    fn list_worker() -> ListWorker {
+        ListWorker { id: 5, name: "Ana".into(), task_count: 1 }
+    }
+
+    fn list_tasks() -> Vec<ListTask> {
+        vec![
+            ListTask { id: 7, name: "Tiles".into(), status: TaskStatus::Assigned },
+            ListTask { id: 8, name: "Gutters".into(), status: TaskStatus::Unassigned },
+        ]
+    }
+
+    fn role() -> Role {
+        Role {
+            id: "admin".into(),
+            perms: vec![Perm::View, Perm::CreateProject, Perm::AssignTask, Perm::ManageUsers],
+        }
+    }
+
+    domain_derive! {
+        pub struct Defaults {
+            /// Required, by default.
+            #[serde(alias = "default")]
+            pub required: u64,
+            #[serde(default)]
+            pub plain: u64,
+            #[serde(default = "Default::default")]
+            pub with_path: u64,
+            #[serde(alias = "later", default)]
+            pub later: u64,
+            #[serde(skip_serializing_if = "Vec::is_empty", default = "Vec::new")]
+            pub later_with_path: Vec<u64>,
+            pub maybe: Option<u64>,
+        }
+    }
+
+    #[test]
+    fn only_serde_defaults_make_fields_optional() {
+        let mut defs = Definitions::new();
+        Defaults::schema(&mut defs);
+        assert_eq!(defs["Defaults"]["required"], json!(["required"]));
+    }
+
+    #[test]
+    fn list_types() {
+        assert_golden(&list_tasks()[0], golden!("list_task"));
+        assert_golden(&list_project(), golden!("list_project"));
+        assert_golden(&list_worker(), golden!("list_worker"));
+        assert_golden(
+            &Page { items: vec![list_project()], offset: 50, total: 51 },
+            golden!("page"),
+            );
+    }
+
+    #[test]
+    fn users_and_roles() {
+        assert_golden(&role(), golden!("role"));
+        assert_golden(
+            &User { id: "local|admin".into(), roles: vec![role()], worker_id: Some(5) },
+            golden!("user"),
+            );
+    }
+
+    #[test]
+    fn details() {
+        assert_golden(
+            &ProjectDetails { id: 2, name: "Roof".into(), tasks: list_tasks(), version: 4 },
+            golden!("project_details"),
+            );
+        assert_golden(
+            &WorkerDetails { id: 5, name: "Ana".into(), tasks: list_tasks()[..1].to_vec(), version: 2 },
+            golden!("worker_details"),
+            );
+    }
+
+    #[test]
+    fn task_details_worker_is_null_when_unassigned() {
+        assert_golden(
+            &TaskDetails { id: 7, name: "Tiles".into(), project: list_project(), worker: Some(list_worker()), version: 3 },
+            golden!("task_details_assigned"),
+            );
+        assert_golden(
+            &TaskDetails { id: 8, name: "Gutters".into(), project: list_project(), worker: None, version: 1 },
+            golden!("task_details_unassigned"),
+            );
+
+        // Servers that leave the key out entirely mean the same thing.
+        let task: TaskDetails = serde_json::from_str(
+            r#"{"id":8,"name":"Gutters","project":{"id":2,"name":"Roof"}}"#
+            ).unwrap();
+        assert!(task.worker.is_none());
+        assert_eq!(task.project.task_count, 0);
+        assert_eq!(task.version, 0);
+    }
+
+    #[test]
+    fn params() {
+        assert_golden(&AddProjectParams { name: "Roof".into() }, golden!("add_project_params"));
+        assert_golden(&AddWorkerParams { name: "Ana".into() }, golden!("add_worker_params"));
+        assert_golden(
+            &AddTaskParams { name: "Tiles".into(), project_id: 2, version: 4 },
+            golden!("add_task_params"),
+            );
+        assert_golden(
+            &AssignTaskParams { task_id: 7, worker_id: 5, version: 3 },
+            golden!("assign_task_params"),
+            );
+        assert_golden(&UnassignTaskParams { task_id: 7, version: 3 }, golden!("unassign_task_params"));
+    }
+
+    #[test]
+    fn events() {
+        let task = TaskDetails { id: 7, name: "Tiles".into(), project: list_project(), worker: None, version: 2 };
+        assert_golden(&Event::Updated(Entity::Task(task)), golden!("event_updated"));
+        assert_golden(&Event::Deleted(EntityRef::Project(2)), golden!("event_deleted"));
+    }
+
+    // What `fetch!` unwraps: serde's encoding of Result<T, String>.
+    #[test]
+    fn result_envelope() {
+        let ok: Result<ProjectDetails, String> =
+            Ok(ProjectDetails { id: 2, name: "Roof".into(), tasks: vec![], version: 4 });
+        assert_golden(&ok, golden!("envelope_ok"));
+
+        let missing: Result<Option<TaskDetails>, String> = Ok(None);
+        assert_golden(&missing, golden!("envelope_ok_null"));
+        let done: Result<(), String> = Ok(());
+        assert_golden(&done, golden!("envelope_ok_null"));
+
+        let err: Result<ProjectDetails, String> = Err("no such project".into());
+        assert_golden(&err, golden!("envelope_err"));
+    }
+
+    #[test]
+    fn perms_are_integers_and_unknown_codes_survive() {
+        let role: Role = serde_json::from_str(r#"{"id":"x","perms":[3,0,42]}"#).unwrap();
+        assert_eq!(role.perms, vec![Perm::ManageUsers, Perm::View, Perm::Unknown(42)]);
+        assert_eq!(serde_json::to_string(&role).unwrap(), r#"{"id":"x","perms":[3,0,42]}"#);
+    }
+
+    #[test]
+    fn effective_perms_are_the_union_of_roles() {
+        let user = User {
+            id: "u".into(),
+            worker_id: None,
+            roles: vec![
+                Role { id: "viewer".into(), perms: vec![Perm::View] },
+                Role { id: "planner".into(), perms: vec![Perm::CreateTask, Perm::Unknown(42)] },
+            ],
+        };
+        let perms = user.perms();
+        assert!(perms.has(Perm::View));
+        assert!(perms.has(Perm::CreateTask));
+        assert!(!perms.has(Perm::AssignTask));
+        assert!(!perms.has(Perm::Unknown(42)));
+    }
+
+    #[test]
+    fn task_status_is_a_bare_string() {
+        assert_eq!(serde_json::to_string(&TaskStatus::Unassigned).unwrap(), r#""Unassigned""#);
+        let task: ListTask = serde_json::from_str(r#"{"id":1,"name":"x"}"#).unwrap();
+        assert_eq!(task.status, TaskStatus::Unassigned);
     }
 }
@@ -0,0 +1,25 @@
+// Text typed into a scene's form but not submitted yet, kept in storage
+// so it survives navigating away or reloading. Drafts are keyed by the
+// scene's hash path, so each project has its own new-task draft.
+
+use crate::services::*;
+
+const DRAFT_PREFIX: &str = "draft.";
+
+fn key(path: &str) -> String {
+    format!("{}{}", DRAFT_PREFIX, path)
+}
+
+// An empty draft is no draft.
+pub fn save_draft(storage: &mut dyn Storage, path: &str, text: &str) {
+    if text.is_empty() {
+        storage.remove(&key(path));
+    }
+    else {
+        storage.store(&key(path), text.into());
+    }
+}
+
+pub fn restore_draft(storage: &dyn Storage, path: &str) -> Option<String> {
+    storage.restore(&key(path))
+}
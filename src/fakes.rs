@@ -0,0 +1,438 @@
+// In-memory stand-ins for the services in services.rs, and a harness that
+// runs `update` against them the way Yew would: one message at a time,
+// with anything sent back queued until the current message is done.
+
+use std::cell::{ Cell, RefCell };
+use std::collections::{ HashMap, VecDeque };
+use std::rc::Rc;
+use serde::Serialize;
+use yew::Callback;
+use crate::authservice::*;
+use crate::locservice::*;
+use crate::model::*;
+use crate::msg::*;
+use crate::config::*;
+use crate::services::*;
+use crate::domain::Event;
+
+// Each with whether it's left alone by `cancel`.
+type Pending = Rc<RefCell<VecDeque<(ApiRequest, Callback<ApiResponse>, bool)>>>;
+
+#[derive(Default)]
+pub struct FakeHttp {
+    pending: Pending,
+}
+
+// Requests complete in the order they were sent.
+impl Http for FakeHttp {
+    fn fetch(&mut self, req: ApiRequest, callback: Callback<ApiResponse>) {
+        let detached = req.method == Method::Post;
+        if !detached {
+            self.cancel();
+        }
+        self.pending.borrow_mut().push_back((req, callback, detached));
+    }
+
+    fn poll(&mut self, req: ApiRequest, callback: Callback<ApiResponse>) {
+        self.pending.borrow_mut().push_back((req, callback, true));
+    }
+
+    fn cancel(&mut self) {
+        self.pending.borrow_mut().retain(|(_, _, detached)| *detached);
+    }
+}
+
+// Setting the hash "fires popstate" like a browser does, but only if the
+// hash actually changed.
+#[derive(Default)]
+pub struct FakeLocation {
+    hash: Rc<RefCell<String>>,
+    callback: Rc<RefCell<Option<Callback<()>>>>,
+}
+
+impl Location for FakeLocation {
+    fn init(&mut self, callback: Callback<()>) {
+        *self.callback.borrow_mut() = Some(callback);
+    }
+
+    fn get_hash_path(&self) -> Vec<String> {
+        hash_path(&self.hash.borrow())
+    }
+
+    fn get_hash_query(&self) -> String {
+        hash_query(&self.hash.borrow())
+    }
+
+    fn set_hash_path(&self, path: String) {
+        let hash = format!("#/{}", path);
+        if *self.hash.borrow() != hash {
+            *self.hash.borrow_mut() = hash;
+            if let Some(callback) = &*self.callback.borrow() {
+                callback.emit(());
+            }
+        }
+    }
+}
+
+type AuthCallback = Rc<RefCell<Option<Callback<AuthState>>>>;
+
+#[derive(Default)]
+pub struct FakeAuth {
+    callback: AuthCallback,
+    refresh: AuthCallback,
+}
+
+impl Auth for FakeAuth {
+    fn init(&mut self, callback: Callback<AuthState>) {
+        *self.callback.borrow_mut() = Some(callback);
+    }
+
+    fn login(&mut self) {}
+    fn logout(&mut self) {}
+
+    fn refresh(&mut self, callback: Callback<AuthState>) {
+        *self.refresh.borrow_mut() = Some(callback);
+    }
+}
+
+#[derive(Default,Clone)]
+pub struct FakeStorage {
+    items: Rc<RefCell<HashMap<String, String>>>,
+    writes: Rc<Cell<usize>>,
+}
+
+impl FakeStorage {
+    // How many times anything has been stored.
+    pub fn writes(&self) -> usize {
+        self.writes.get()
+    }
+}
+
+impl Storage for FakeStorage {
+    fn store(&mut self, key: &str, value: String) {
+        self.writes.set(self.writes.get() + 1);
+        self.items.borrow_mut().insert(key.into(), value);
+    }
+
+    fn restore(&self, key: &str) -> Option<String> {
+        self.items.borrow().get(key).cloned()
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.items.borrow_mut().remove(key);
+    }
+}
+
+#[derive(Default)]
+pub struct FakeLogger {
+    lines: Rc<RefCell<Vec<String>>>,
+}
+
+impl Logger for FakeLogger {
+    fn log(&self, msg: &str) {
+        self.lines.borrow_mut().push(msg.into());
+    }
+}
+
+// When it comes due, and what to call.
+type Scheduled = (f64, Callback<()>);
+
+// Time only moves when a test advances it.
+#[derive(Default,Clone)]
+pub struct FakeTimer {
+    now: Rc<Cell<f64>>,
+    scheduled: Rc<RefCell<HashMap<&'static str, Scheduled>>>,
+}
+
+impl Timer for FakeTimer {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+
+    fn schedule(&mut self, name: &'static str, delay_ms: u64, callback: Callback<()>) {
+        let due = self.now.get() + delay_ms as f64;
+        self.scheduled.borrow_mut().insert(name, (due, callback));
+    }
+}
+
+// Online until a test says otherwise.
+#[derive(Default,Clone)]
+pub struct FakeNetwork {
+    offline: Rc<Cell<bool>>,
+    callback: Rc<RefCell<Option<Callback<bool>>>>,
+}
+
+impl Network for FakeNetwork {
+    fn init(&mut self, callback: Callback<bool>) {
+        *self.callback.borrow_mut() = Some(callback);
+    }
+
+    fn is_online(&self) -> bool {
+        !self.offline.get()
+    }
+}
+
+type SocketCallbacks = Rc<RefCell<Option<(Callback<String>, Callback<bool>)>>>;
+
+// Opens, closes and gets messages only when a test says so.
+#[derive(Default,Clone)]
+pub struct FakeSocket {
+    urls: Rc<RefCell<Vec<String>>>,
+    callbacks: SocketCallbacks,
+}
+
+impl Socket for FakeSocket {
+    fn connect(&mut self, url: &str, on_message: Callback<String>, on_open: Callback<bool>) {
+        self.urls.borrow_mut().push(url.into());
+        *self.callbacks.borrow_mut() = Some((on_message, on_open));
+    }
+}
+
+// Ticks when a test says so.
+#[derive(Default,Clone)]
+pub struct FakeInterval {
+    callback: Rc<RefCell<Option<Callback<()>>>>,
+}
+
+impl Interval for FakeInterval {
+    fn start(&mut self, _every_ms: u64, callback: Callback<()>) {
+        *self.callback.borrow_mut() = Some(callback);
+    }
+}
+
+// Visible until a test says otherwise.
+#[derive(Default,Clone)]
+pub struct FakeVisibility {
+    hidden: Rc<Cell<bool>>,
+}
+
+impl Visibility for FakeVisibility {
+    fn is_visible(&self) -> bool {
+        !self.hidden.get()
+    }
+}
+
+pub struct Harness {
+    pub model: Model,
+    queue: Rc<RefCell<VecDeque<Msg>>>,
+    pending: Pending,
+    hash: Rc<RefCell<String>>,
+    auth: AuthCallback,
+    refresh: AuthCallback,
+    timer: FakeTimer,
+    storage: FakeStorage,
+    network: FakeNetwork,
+    socket: FakeSocket,
+    interval: FakeInterval,
+    visibility: FakeVisibility,
+    pub log: Rc<RefCell<Vec<String>>>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let auth = FakeAuth::default();
+        let callback = auth.callback.clone();
+        let refresh = auth.refresh.clone();
+
+        let mut h = Self::with_auth(Box::new(auth));
+        h.auth = callback;
+        h.refresh = refresh;
+        h
+    }
+
+    // Runs against a real provider; `finish_auth` and `finish_refresh`
+    // are then up to it.
+    pub fn with_auth(auth: Box<dyn Auth>) -> Self {
+        let queue = Rc::new(RefCell::new(VecDeque::new()));
+        let sink = {
+            let queue = queue.clone();
+            Callback::from(move |msg| queue.borrow_mut().push_back(msg))
+        };
+
+        let http = FakeHttp::default();
+        let loc = FakeLocation::default();
+        let storage = FakeStorage::default();
+        let logger = FakeLogger::default();
+        let timer = FakeTimer::default();
+        let network = FakeNetwork::default();
+        let socket = FakeSocket::default();
+        let interval = FakeInterval::default();
+        let visibility = FakeVisibility::default();
+
+        Self {
+            queue,
+            pending: http.pending.clone(),
+            hash: loc.hash.clone(),
+            auth: AuthCallback::default(),
+            refresh: AuthCallback::default(),
+            timer: timer.clone(),
+            storage: storage.clone(),
+            network: network.clone(),
+            socket: socket.clone(),
+            interval: interval.clone(),
+            visibility: visibility.clone(),
+            log: logger.lines.clone(),
+            model: Model::with_services(
+                Config::embedded().unwrap(),
+                Link::new(sink),
+                Services {
+                    auth,
+                    loc: Box::new(loc),
+                    http: Box::new(http),
+                    storage: Box::new(storage),
+                    logger: Box::new(logger),
+                    timer: Box::new(timer),
+                    network: Box::new(network),
+                    socket: Box::new(socket),
+                    interval: Box::new(interval),
+                    visibility: Box::new(visibility),
+                },
+                ),
+        }
+    }
+
+    // A fresh app on the same local storage, as after a page reload.
+    pub fn reload(&self) -> Self {
+        let mut h = Self::new();
+        h.storage = self.storage.clone();
+        h.model.storage = Box::new(self.storage.clone());
+        h
+    }
+
+    // Sends `msg` and everything it sends back, until the queue is empty.
+    pub fn send(&mut self, msg: Msg) {
+        self.queue.borrow_mut().push_back(msg);
+        self.run();
+    }
+
+    fn run(&mut self) {
+        loop {
+            let next = self.queue.borrow_mut().pop_front();
+            match next {
+                Some(msg) => { update(&mut self.model, msg); }
+                None      => break,
+            }
+        }
+    }
+
+    // Sets the hash behind the model's back, as a page load would.
+    pub fn set_hash(&mut self, hash: &str) {
+        *self.hash.borrow_mut() = hash.into();
+    }
+
+    // The user typed a URL or pressed back: the hash changes, then the
+    // popstate arrives.
+    pub fn navigate(&mut self, hash: &str) {
+        self.set_hash(hash);
+        self.send(Msg::Route);
+    }
+
+    pub fn storage(&self) -> FakeStorage {
+        self.storage.clone()
+    }
+
+    pub fn hash(&self) -> String {
+        self.hash.borrow().clone()
+    }
+
+    pub fn finish_auth(&mut self, state: AuthState) {
+        let callback = self.auth.borrow_mut().take().expect("auth not started");
+        callback.emit(state);
+        self.run();
+    }
+
+    // Completes the token refresh in flight.
+    pub fn finish_refresh(&mut self, state: AuthState) {
+        let callback = self.refresh.borrow_mut().take().expect("no refresh started");
+        callback.emit(state);
+        self.run();
+    }
+
+    pub fn refreshing(&self) -> bool {
+        self.refresh.borrow().is_some()
+    }
+
+    pub fn now(&self) -> f64 {
+        self.timer.now()
+    }
+
+    // Moves the clock on, firing the scheduled callbacks that come due,
+    // soonest first.
+    pub fn advance(&mut self, ms: f64) {
+        let now = self.timer.now.get() + ms;
+        self.timer.now.set(now);
+        loop {
+            let next = self.timer.scheduled.borrow().iter()
+                .filter(|(_, (at, _))| *at <= now)
+                .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b))
+                .map(|(name, _)| *name);
+            match next {
+                Some(name) => {
+                    let (_, callback) = self.timer.scheduled.borrow_mut().remove(name).unwrap();
+                    callback.emit(());
+                    self.run();
+                }
+                None => break,
+            }
+        }
+    }
+
+    // The browser losing or getting back its connection. Requests in
+    // flight are left alone: answer them with `respond_raw(0, "")` for a
+    // network error.
+    pub fn set_online(&mut self, online: bool) {
+        self.network.offline.set(!online);
+        if let Some(callback) = &*self.network.callback.borrow() {
+            callback.emit(online);
+        }
+        self.run();
+    }
+
+    // Every URL the socket was asked to connect to, in order.
+    pub fn connections(&self) -> Vec<String> {
+        self.socket.urls.borrow().clone()
+    }
+
+    // The latest connection opening, or closing or failing to.
+    pub fn set_socket_open(&mut self, open: bool) {
+        let on_open = self.socket.callbacks.borrow().as_ref().expect("never connected").1.clone();
+        on_open.emit(open);
+        self.run();
+    }
+
+    // The server sending `event` down the latest connection.
+    pub fn push_event(&mut self, event: &Event) {
+        let on_message = self.socket.callbacks.borrow().as_ref().expect("never connected").0.clone();
+        on_message.emit(serde_json::to_string(event).unwrap());
+        self.run();
+    }
+
+    // The interval started on Msg::Init coming round.
+    pub fn tick(&mut self) {
+        let callback = self.interval.callback.borrow().clone().expect("no interval started");
+        callback.emit(());
+        self.run();
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visibility.hidden.set(!visible);
+    }
+
+    // The oldest request in flight, if any.
+    pub fn request(&self) -> Option<ApiRequest> {
+        self.pending.borrow().front().map(|(req, _, _)| req.clone())
+    }
+
+    // Completes the oldest request in flight with `Ok(body)`, the way the
+    // API wraps every response.
+    pub fn respond<T: Serialize>(&mut self, body: &T) {
+        let ok: Result<&T, String> = Ok(body);
+        self.respond_raw(200, &serde_json::to_string(&ok).unwrap());
+    }
+
+    pub fn respond_raw(&mut self, status: u16, body: &str) {
+        let (_, callback, _) = self.pending.borrow_mut().pop_front().expect("no request in flight");
+        callback.emit(ApiResponse { status, body: body.into() });
+        self.run();
+    }
+}
@@ -1,13 +1,37 @@
 #![recursion_limit="256"]
 
-mod domain;
+pub mod domain;
+pub mod schema;
+pub mod contract;
+pub mod liststate;
 mod model;
 mod msg;
 mod component;
 mod render;
 mod authservice;
+mod localauth;
+mod config;
+mod prefs;
+mod drafts;
+mod cache;
+mod outbox;
+mod live;
 mod locservice;
 mod views;
+mod services;
+#[cfg(test)]
+mod fakes;
+#[cfg(test)]
+mod snapshots;
+mod listwindow;
 
 pub use model::Model;
 pub use msg::Msg;
+
+// stdweb's native build links against emscripten's JS bridge. Unit tests
+// never reach the DOM, they only need this symbol to exist to link.
+#[cfg(test)]
+#[no_mangle]
+pub extern "C" fn emscripten_asm_const_int() -> i32 {
+    unreachable!("JS called from a unit test")
+}
@@ -0,0 +1,380 @@
+use std::cmp::Ordering;
+use serde::{ Serialize, Deserialize };
+use crate::domain::*;
+
+// Serialized with the same names as `as_str`.
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    Id,
+    #[serde(rename = "tasks")]
+    TaskCount,
+    Status,
+}
+
+impl SortKey {
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name      => "Name",
+            SortKey::Id        => "Id",
+            SortKey::TaskCount => "Tasks",
+            SortKey::Status    => "Status",
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortKey::Name      => "name",
+            SortKey::Id        => "id",
+            SortKey::TaskCount => "tasks",
+            SortKey::Status    => "status",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<SortKey> {
+        match s {
+            "name"   => Some(SortKey::Name),
+            "id"     => Some(SortKey::Id),
+            "tasks"  => Some(SortKey::TaskCount),
+            "status" => Some(SortKey::Status),
+            _        => None,
+        }
+    }
+}
+
+// Anything that can be shown in a filterable, sortable list.
+pub trait Listable {
+    fn sort_keys() -> &'static [SortKey];
+    fn filter_text(&self) -> &str;
+    fn cmp_by(&self, other: &Self, key: SortKey) -> Ordering;
+}
+
+impl Listable for ListProject {
+    fn sort_keys() -> &'static [SortKey] {
+        &[SortKey::Name, SortKey::Id, SortKey::TaskCount]
+    }
+
+    fn filter_text(&self) -> &str {
+        &self.name
+    }
+
+    fn cmp_by(&self, other: &Self, key: SortKey) -> Ordering {
+        match key {
+            SortKey::TaskCount => self.task_count.cmp(&other.task_count),
+            SortKey::Id        => self.id.cmp(&other.id),
+            _                  => cmp_names(&self.name, &other.name),
+        }
+    }
+}
+
+impl Listable for ListWorker {
+    fn sort_keys() -> &'static [SortKey] {
+        &[SortKey::Name, SortKey::Id, SortKey::TaskCount]
+    }
+
+    fn filter_text(&self) -> &str {
+        &self.name
+    }
+
+    fn cmp_by(&self, other: &Self, key: SortKey) -> Ordering {
+        match key {
+            SortKey::TaskCount => self.task_count.cmp(&other.task_count),
+            SortKey::Id        => self.id.cmp(&other.id),
+            _                  => cmp_names(&self.name, &other.name),
+        }
+    }
+}
+
+impl Listable for ListTask {
+    fn sort_keys() -> &'static [SortKey] {
+        &[SortKey::Name, SortKey::Id, SortKey::Status]
+    }
+
+    fn filter_text(&self) -> &str {
+        &self.name
+    }
+
+    fn cmp_by(&self, other: &Self, key: SortKey) -> Ordering {
+        match key {
+            SortKey::Status => self.status.cmp(&other.status),
+            SortKey::Id     => self.id.cmp(&other.id),
+            _               => cmp_names(&self.name, &other.name),
+        }
+    }
+}
+
+impl Listable for User {
+    fn sort_keys() -> &'static [SortKey] {
+        &[SortKey::Id]
+    }
+
+    fn filter_text(&self) -> &str {
+        &self.id
+    }
+
+    fn cmp_by(&self, other: &Self, _key: SortKey) -> Ordering {
+        cmp_names(&self.id, &other.id)
+    }
+}
+
+fn cmp_names(a: &str, b: &str) -> Ordering {
+    a.to_lowercase().cmp(&b.to_lowercase())
+}
+
+// Case-insensitive subsequence match: "prj" matches "Project".
+pub fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|p| text.any(|t| t == p))
+}
+
+// Filter, sort and page settings for the list in the current scene.
+// Round-trips through the query part of the hash path,
+// e.g. "#/projects?q=web&sort=tasks&desc&page=2".
+// Lists inside details are whole and filtered and sorted here. Paged lists
+// send `search_query` with the fetch and the server does it before paging,
+// so `apply` finds the page already in order.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ListState {
+    pub filter: String,
+    pub sort: SortKey,
+    pub desc: bool,
+    pub page: usize,
+}
+
+impl Default for ListState {
+    fn default() -> Self {
+        Self {
+            filter: String::new(),
+            sort: SortKey::Name,
+            desc: false,
+            page: 0,
+        }
+    }
+}
+
+impl ListState {
+    pub fn apply<'a, T: Listable>(&self, items: &'a [T]) -> Vec<&'a T> {
+        let sort = if T::sort_keys().contains(&self.sort) {
+            self.sort
+        }
+        else {
+            T::sort_keys()[0]
+        };
+
+        let mut visible: Vec<&T> = items
+            .iter()
+            .filter(|item| fuzzy_match(&self.filter, item.filter_text()))
+            .collect();
+
+        visible.sort_by(|a, b| {
+            let ord = a.cmp_by(b, sort);
+            if self.desc { ord.reverse() } else { ord }
+        });
+
+        visible
+    }
+
+    // Clicking the active sort key flips the direction.
+    pub fn toggle_sort(&mut self, key: SortKey) {
+        if self.sort == key {
+            self.desc = !self.desc;
+        }
+        else {
+            self.sort = key;
+            self.desc = false;
+        }
+    }
+
+    pub fn from_query(query: &str) -> Self {
+        let mut state = Self::default();
+
+        for pair in query.split('&').filter(|s| !s.is_empty()) {
+            let mut kv = pair.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = percent_decode(kv.next().unwrap_or(""));
+
+            match key {
+                "q"    => state.filter = value,
+                "sort" => state.sort = SortKey::parse(&value).unwrap_or(state.sort),
+                "desc" => state.desc = true,
+                "page" => state.page = parse_page(&value),
+                _      => (),
+            }
+        }
+
+        state
+    }
+
+    pub fn to_query(&self) -> String {
+        let mut parts = self.search_parts();
+        if self.page > 0 {
+            parts.push(format!("page={}", self.page + 1));
+        }
+
+        parts.join("&")
+    }
+
+    // The query without the page, for the server (see contract.rs).
+    pub fn search_query(&self) -> String {
+        self.search_parts().join("&")
+    }
+
+    fn search_parts(&self) -> Vec<String> {
+        let default = Self::default();
+        let mut parts = Vec::new();
+
+        if !self.filter.is_empty() {
+            parts.push(format!("q={}", percent_encode(&self.filter)));
+        }
+        if self.sort != default.sort {
+            parts.push(format!("sort={}", self.sort.as_str()));
+        }
+        if self.desc {
+            parts.push("desc".into());
+        }
+
+        parts
+    }
+}
+
+// Pages are 1-based in the URL, 0-based everywhere else.
+fn parse_page(s: &str) -> usize {
+    s.parse::<usize>().unwrap_or(1).max(1) - 1
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~'
+                => out.push(b as char),
+            _   => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = s.get(i + 1 .. i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match hex {
+            Some(b) => { out.push(b); i += 3; }
+            None    => { out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] }); i += 1; }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(id: ProjectId, name: &str, task_count: usize) -> ListProject {
+        ListProject { id, name: name.into(), task_count }
+    }
+
+    fn names<T: Listable>(list: &ListState, items: &[T]) -> Vec<String> {
+        list.apply(items).iter().map(|item| item.filter_text().to_string()).collect()
+    }
+
+    #[test]
+    fn queries_round_trip() {
+        let list = ListState { filter: "web app/ü".into(), sort: SortKey::TaskCount, desc: true, page: 2 };
+        let query = list.to_query();
+        assert_eq!(query, "q=web%20app%2F%C3%BC&sort=tasks&desc&page=3");
+        assert_eq!(ListState::from_query(&query), list);
+        assert_eq!(list.search_query(), "q=web%20app%2F%C3%BC&sort=tasks&desc");
+
+        assert_eq!(ListState::default().to_query(), "");
+        assert_eq!(ListState::from_query(""), ListState::default());
+    }
+
+    #[test]
+    fn malformed_queries_fall_back_to_defaults() {
+        for query in ["page=0", "page=-1", "page=abc", "page=", "sort=bogus", "sort", "&&", "x=1&=2", "="] {
+            assert_eq!(ListState::from_query(query), ListState::default(), "{}", query);
+        }
+        assert!(ListState::from_query("desc=no").desc);
+        assert_eq!(ListState::from_query("q=a=b").filter, "a=b");
+    }
+
+    #[test]
+    fn percent_decoding_keeps_what_it_cant_decode() {
+        assert_eq!(percent_decode("a%zzb"), "a%zzb");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("a+b%20c"), "a b c");
+        assert_eq!(percent_decode("%E2%9C%93"), "✓");
+        assert_eq!(percent_decode("ü%C3%BC"), "üü");
+        // Half a character.
+        assert_eq!(percent_decode("%C3"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn fuzzy_matches_in_order_ignoring_case_and_spaces() {
+        assert!(fuzzy_match("prj", "Project"));
+        assert!(fuzzy_match("", "anything"));
+        assert!(fuzzy_match("mo app", "Mobile app"));
+        assert!(fuzzy_match("ÉCO", "école"));
+        assert!(!fuzzy_match("jp", "Project"));
+        assert!(!fuzzy_match("projects", "Project"));
+    }
+
+    #[test]
+    fn each_sort_key_orders_its_column() {
+        let projects = [project(2, "beta", 5), project(1, "Alpha", 0), project(3, "gamma", 2)];
+        let mut list = ListState::default();
+        assert_eq!(names(&list, &projects), ["Alpha", "beta", "gamma"]);
+        list.sort = SortKey::Id;
+        assert_eq!(names(&list, &projects), ["Alpha", "beta", "gamma"]);
+        list.sort = SortKey::TaskCount;
+        list.desc = true;
+        assert_eq!(names(&list, &projects), ["beta", "gamma", "Alpha"]);
+
+        let tasks = [
+            ListTask { id: 1, name: "b".into(), status: TaskStatus::Assigned },
+            ListTask { id: 2, name: "a".into(), status: TaskStatus::Unassigned },
+        ];
+        list.sort = SortKey::Status;
+        list.desc = false;
+        assert_eq!(names(&list, &tasks), ["a", "b"]);
+        // Tasks have no count: by name instead.
+        list.sort = SortKey::TaskCount;
+        list.desc = true;
+        assert_eq!(names(&list, &tasks), ["b", "a"]);
+    }
+
+    #[test]
+    fn ties_keep_the_order_they_came_in_either_way() {
+        let projects = [project(1, "b", 1), project(2, "a", 1), project(3, "c", 0)];
+        let mut list = ListState { sort: SortKey::TaskCount, ..ListState::default() };
+        assert_eq!(names(&list, &projects), ["c", "b", "a"]);
+        list.desc = true;
+        assert_eq!(names(&list, &projects), ["b", "a", "c"]);
+
+        let same = [project(1, "Web", 0), project(2, "web", 0)];
+        assert_eq!(names(&ListState::default(), &same), ["Web", "web"]);
+    }
+
+    #[test]
+    fn the_active_key_flips_direction() {
+        let mut list = ListState::default();
+        list.toggle_sort(SortKey::Name);
+        assert!(list.desc);
+        list.toggle_sort(SortKey::Id);
+        assert_eq!((list.sort, list.desc), (SortKey::Id, false));
+    }
+}
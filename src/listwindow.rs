@@ -0,0 +1,101 @@
+use std::ops::Range;
+
+// Rows in a windowed list are fixed height so the visible range can be
+// computed from the scroll offset alone. `row_style` holds them to it: a
+// name too long for one line is cut short rather than wrapped, which
+// would push every row below it out of place.
+pub const ROW_HEIGHT: usize = 24;
+pub const VIEWPORT_ROWS: usize = 20;
+
+// Extra rows rendered above and below the viewport so fast scrolling
+// doesn't flash empty space before the next update.
+const OVERSCAN: usize = 5;
+
+// Scroll position of a windowed list. Only rows in `range()` are
+// materialized; the rest of the scroll height is padding.
+#[derive(Debug,Clone,Copy,Default,PartialEq)]
+pub struct ListWindow {
+    pub scroll_top: usize,
+}
+
+impl ListWindow {
+    pub fn viewport_height() -> usize {
+        ROW_HEIGHT * VIEWPORT_ROWS
+    }
+
+    pub fn row_style() -> String {
+        format!(
+            "height: {0}px; line-height: {0}px; overflow: hidden; white-space: nowrap; text-overflow: ellipsis;",
+            ROW_HEIGHT
+            )
+    }
+
+    pub fn range(&self, len: usize) -> Range<usize> {
+        let first = self.scroll_top / ROW_HEIGHT;
+        let start = first.saturating_sub(OVERSCAN).min(len);
+        let end = (first + VIEWPORT_ROWS + OVERSCAN).min(len);
+        start..end
+    }
+
+    pub fn pad_top(&self, len: usize) -> usize {
+        self.range(len).start * ROW_HEIGHT
+    }
+
+    pub fn pad_bottom(&self, len: usize) -> usize {
+        (len - self.range(len).end) * ROW_HEIGHT
+    }
+
+    // Returns true if the new offset changes which rows are visible,
+    // i.e. whether a re-render is needed.
+    pub fn scroll_to(&mut self, scroll_top: usize, len: usize) -> bool {
+        let before = self.range(len);
+        self.scroll_top = scroll_top;
+        self.range(len) != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at_row(row: usize) -> ListWindow {
+        ListWindow { scroll_top: row * ROW_HEIGHT }
+    }
+
+    #[test]
+    fn short_lists_are_shown_whole() {
+        let window = ListWindow::default();
+        assert_eq!(window.range(0), 0..0);
+        assert_eq!(window.range(7), 0..7);
+        assert_eq!((window.pad_top(7), window.pad_bottom(7)), (0, 0));
+        // A scroll left over from a longer list.
+        assert_eq!(at_row(50).range(7), 7..7);
+        assert_eq!((at_row(50).pad_top(7), at_row(50).pad_bottom(7)), (7 * ROW_HEIGHT, 0));
+    }
+
+    #[test]
+    fn padding_stands_in_for_the_rows_left_out() {
+        let window = at_row(40);
+        assert_eq!(window.range(100), 35..65);
+        assert_eq!(window.pad_top(100), 35 * ROW_HEIGHT);
+        assert_eq!(window.pad_bottom(100), 35 * ROW_HEIGHT);
+    }
+
+    #[test]
+    fn scrolling_past_the_end_stops_at_the_last_row() {
+        let window = at_row(1_000);
+        assert_eq!(window.range(100), 100..100);
+        assert_eq!(window.pad_top(100) + window.pad_bottom(100), 100 * ROW_HEIGHT);
+        assert_eq!(at_row(90).range(100), 85..100);
+    }
+
+    #[test]
+    fn scrolling_within_a_row_needs_no_render() {
+        let mut window = at_row(40);
+        assert!(!window.scroll_to(40 * ROW_HEIGHT + ROW_HEIGHT - 1, 100));
+        assert_eq!(window.scroll_top, 41 * ROW_HEIGHT - 1);
+        assert!(window.scroll_to(41 * ROW_HEIGHT, 100));
+        // Nothing moves in a list that fits.
+        assert!(!ListWindow::default().scroll_to(5 * ROW_HEIGHT, 10));
+    }
+}
@@ -0,0 +1,46 @@
+// Changes made by others, as they happen. With "live" set to websocket in
+// the config, the app listens to the API's event stream (see
+// contract::EVENTS_PATH) from Msg::Init on. Each Event goes into the cache
+// and the scene on screen (see Cache::receive).
+//
+// A connection that drops, or can't be made, is tried again after a wait
+// that doubles each time, from RECONNECT_MIN_MS up to RECONNECT_MAX_MS.
+// Once it's back, the scene is fetched again for whatever was missed.
+
+pub const RECONNECT_MIN_MS: u64 = 1_000;
+pub const RECONNECT_MAX_MS: u64 = 30_000;
+
+#[derive(Default)]
+pub struct Live {
+    pub open: bool,
+    // Whether it has been open before, so events may have been missed.
+    pub was_open: bool,
+    // Failed tries since it was last open.
+    pub retries: u32,
+    // A try is scheduled. A failed connection reports an error, then a
+    // close; the second doesn't need another.
+    pub waiting: bool,
+}
+
+impl Live {
+    // How long to wait before the next try.
+    pub fn backoff(&self) -> u64 {
+        RECONNECT_MIN_MS
+            .saturating_mul(1 << self.retries.min(16))
+            .min(RECONNECT_MAX_MS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_double_up_to_the_limit() {
+        let waits: Vec<u64> = (0..8)
+            .map(|retries| Live { retries, ..Live::default() }.backoff())
+            .collect();
+        assert_eq!(waits, [1_000, 2_000, 4_000, 8_000, 16_000, 30_000, 30_000, 30_000]);
+        assert_eq!(Live { retries: 1_000, ..Live::default() }.backoff(), RECONNECT_MAX_MS);
+    }
+}
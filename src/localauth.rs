@@ -0,0 +1,140 @@
+// An auth provider for development that needs no identity service.
+// Logging in just picks one of the configured accounts, and the token is
+// the account's user id, which is how the mock backend tells callers
+// apart. The session is kept in storage so it survives a reload.
+
+use yew::Callback;
+use serde::Deserialize;
+use crate::authservice::*;
+use crate::services::*;
+
+const SESSION_KEY: &str = "local_auth.account";
+
+#[derive(Deserialize,Debug,Clone,Default)]
+pub struct LocalConfig {
+    // User ids to offer on the login screen, e.g. "local|admin".
+    pub accounts: Vec<String>,
+}
+
+pub struct LocalProvider {
+    config: LocalConfig,
+    storage: Box<dyn Storage>,
+    callback: Option<Callback<AuthState>>,
+}
+
+impl LocalProvider {
+    pub fn new(config: LocalConfig, storage: Box<dyn Storage>) -> Self {
+        Self {
+            config,
+            storage,
+            callback: None,
+        }
+    }
+
+    // A stored account that is no longer configured doesn't count.
+    fn state(&self) -> AuthState {
+        match self.storage.restore(SESSION_KEY) {
+            Some(account) if self.config.accounts.contains(&account) => {
+                AuthState::Yes(AuthUser {
+                    name: account.clone(),
+                    token: account,
+                    expires_at: None,
+                })
+            }
+            _ => AuthState::No,
+        }
+    }
+
+    // There's no redirect to come back from, so changes are reported
+    // through the callback from `init`.
+    fn notify(&self) {
+        if let Some(callback) = &self.callback {
+            callback.emit(self.state());
+        }
+    }
+}
+
+impl Auth for LocalProvider {
+    fn init(&mut self, callback: Callback<AuthState>) {
+        callback.emit(self.state());
+        self.callback = Some(callback);
+    }
+
+    fn login(&mut self) {
+        if let Some(account) = self.config.accounts.first().cloned() {
+            self.login_as(&account);
+        }
+    }
+
+    fn login_as(&mut self, account: &str) {
+        self.storage.store(SESSION_KEY, account.into());
+        self.notify();
+    }
+
+    fn logout(&mut self) {
+        self.storage.remove(SESSION_KEY);
+        self.notify();
+    }
+
+    fn refresh(&mut self, callback: Callback<AuthState>) {
+        callback.emit(self.state());
+    }
+
+    fn accounts(&self) -> Vec<String> {
+        self.config.accounts.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use crate::fakes::*;
+
+    fn config() -> LocalConfig {
+        LocalConfig { accounts: vec!["local|admin".into(), "local|viewer".into()] }
+    }
+
+    // The provider and everything its callback has been given.
+    fn init(storage: &FakeStorage) -> (LocalProvider, Rc<RefCell<Vec<Option<String>>>>) {
+        let tokens = Rc::new(RefCell::new(Vec::new()));
+        let callback = {
+            let tokens = tokens.clone();
+            Callback::from(move |state: AuthState| tokens.borrow_mut().push(state.token()))
+        };
+        let mut provider = LocalProvider::new(config(), Box::new(storage.clone()));
+        provider.init(callback);
+        (provider, tokens)
+    }
+
+    #[test]
+    fn login_issues_the_account_as_token() {
+        let storage = FakeStorage::default();
+        let (mut provider, tokens) = init(&storage);
+
+        provider.login_as("local|viewer");
+        provider.logout();
+
+        assert_eq!(*tokens.borrow(), vec![None, Some("local|viewer".into()), None]);
+    }
+
+    #[test]
+    fn session_survives_a_reload() {
+        let storage = FakeStorage::default();
+        let (mut provider, _) = init(&storage);
+        provider.login();
+
+        let (_, tokens) = init(&storage);
+        assert_eq!(*tokens.borrow(), vec![Some("local|admin".into())]);
+    }
+
+    #[test]
+    fn unknown_account_is_logged_out() {
+        let storage = FakeStorage::default();
+        let (mut provider, tokens) = init(&storage);
+        provider.login_as("auth0|someone");
+
+        assert_eq!(*tokens.borrow(), vec![None, None]);
+    }
+}
@@ -4,13 +4,13 @@ use crate::domain::*;
 use yew::prelude::*;
 use stdweb::{
     js, 
-    web::Location,
     web::window,
     web::Window,
     web::IEventTarget,
     web::event::PopStateEvent,
 };
 use serde::{ Serialize, Deserialize };
+use crate::services::*;
 
 pub struct LocService;
 
@@ -18,27 +18,53 @@ impl LocService {
     pub fn new() -> Self {
         LocService
     }
-    
-    pub fn init(&mut self, callback: Callback<()>) {
+
+    fn get_hash(&self) -> String {
+        window()
+            .location().expect("could not get location")
+            .hash().unwrap()
+    }
+}
+
+impl Location for LocService {
+    fn init(&mut self, callback: Callback<()>) {
         window().add_event_listener(
             move |_: PopStateEvent| callback.emit(())
             );
     }
 
-    pub fn get_hash_path(&self) -> Vec<String> {
-        window()
-            .location().expect("could not get location")
-            .hash().unwrap()
-            .trim_start_matches("#/")
-            .split_terminator('/')
-            .map(|s:&str| s.into())
-            .collect()
+    fn get_hash_path(&self) -> Vec<String> {
+        hash_path(&self.get_hash())
+    }
+
+    fn get_hash_query(&self) -> String {
+        hash_query(&self.get_hash())
     }
 
-    pub fn set_hash_path(&self, path: String) {
+    fn set_hash_path(&self, path: String) {
         let window = window();
         js! {
             @{window}.location.hash = "#/" + @{path};
         };
     }
 }
+
+// "#/project/3?q=x" -> ["project", "3"]
+pub fn hash_path(hash: &str) -> Vec<String> {
+    hash
+        .trim_start_matches('#')
+        .trim_start_matches('/')
+        .split('?')
+        .next().unwrap_or("")
+        .split_terminator('/')
+        .map(|s:&str| s.into())
+        .collect()
+}
+
+// "#/project/3?q=x" -> "q=x"
+pub fn hash_query(hash: &str) -> String {
+    hash
+        .split_once('?')
+        .map(|(_, query)| query.into())
+        .unwrap_or_default()
+}
@@ -1,53 +1,115 @@
 use yew::prelude::*;
-use yew::services::fetch::*;
-use yew::services::storage::*;
 use crate::authservice::*;
 use crate::locservice::*;
+use crate::services::*;
 use crate::views::*;
 use crate::domain::*;
-
-extern crate askama;
-use askama::Template;
-use stdweb::web::Node;
-use yew::virtual_dom::VNode;
+use crate::config::*;
+use crate::prefs::*;
+use crate::cache::*;
+use crate::outbox::*;
+use crate::live::*;
 
 #[derive(Default)]
 pub struct Temp {
     pub task: Option<TaskDetails>,
+    pub users: Option<Page<User>>,
+    // A GET that got a 401, to send again once the token is fresh.
+    pub retry: Option<(ApiRequest, Callback<ApiResponse>)>,
+    // Likewise for changes, which all have to go again.
+    pub retries: Vec<(ApiRequest, Callback<ApiResponse>)>,
+    // Set by the first Msg::Route, after which an empty hash is the home
+    // scene rather than the one the last visit ended on.
+    pub routed: bool,
 }
 
 pub struct Model {
-    pub auth: AuthService,
-    pub loc: LocService,
-    pub fetcher: FetchService,
-    pub storage: StorageService,
-    pub link: ComponentLink<Model>,
-    pub task: Option<FetchTask>,
+    pub config: Config,
+    pub auth: Box<dyn Auth>,
+    pub loc: Box<dyn Location>,
+    pub http: Box<dyn Http>,
+    pub storage: Box<dyn Storage>,
+    pub logger: Box<dyn Logger>,
+    pub timer: Box<dyn Timer>,
+    pub network: Box<dyn Network>,
+    pub socket: Box<dyn Socket>,
+    pub interval: Box<dyn Interval>,
+    pub visibility: Box<dyn Visibility>,
+    pub link: Link,
     pub scene: Scene,
     pub temp: Temp,
     pub auth_state: AuthState,
+    // Who the API says is logged in, and what they may do.
+    pub user: Option<User>,
+    pub perms: Perms,
+    // Set when a token refresh fails, so the login prompt can say why.
+    pub session_expired: bool,
+    // Set when a guest tries something that needs an account.
+    pub login_to_edit: bool,
+    // Why a change the user made didn't stick, until they move on.
+    pub notice: Option<String>,
+    // Loaded from storage on Msg::Init, saved whenever they change.
+    pub prefs: Prefs,
+    // Details seen this visit, shown at once when going back to them.
+    pub cache: Cache,
+    // Changes waiting to be sent, loaded on Msg::Init.
+    pub outbox: Outbox,
+    pub online: bool,
+    // The event stream, if the config asks for it.
+    pub live: Live,
 }
 
 impl Model {
-    pub fn new(link: ComponentLink<Self>) -> Self {
+    pub fn new(mut link: ComponentLink<Self>) -> Self {
+        let config = Config::embedded().unwrap_or_else(|e| panic!("Bad config: {}", e));
+        let services = Services {
+            auth: provider(&config.auth),
+            loc: Box::new(LocService::new()),
+            http: Box::new(WebHttp::new(config.api_url.clone())),
+            storage: Box::new(WebStorage::new()),
+            logger: Box::new(ConsoleLogger),
+            timer: Box::new(WebTimer::new()),
+            network: Box::new(WebNetwork),
+            socket: Box::new(WebSocket::new()),
+            interval: Box::new(WebInterval::new()),
+            visibility: Box::new(WebVisibility),
+        };
+        Self::with_services(config, Link::new(link.send_back(|msg| msg)), services)
+    }
+
+    // Logged out, as opposed to not knowing yet.
+    pub fn is_guest(&self) -> bool {
+        matches!(self.auth_state, AuthState::No)
+    }
+
+    pub fn with_services(config: Config, link: Link, services: Services) -> Self {
+        let Services { auth, loc, http, storage, logger, timer, network, socket, interval, visibility } = services;
         Self {
-            auth: AuthService::new(),
-            loc: LocService::new(),
-            fetcher: FetchService::new(),
-            storage: StorageService::new(Area::Local),
+            config,
+            auth,
+            loc,
+            http,
+            storage,
+            logger,
+            timer,
+            online: network.is_online(),
+            network,
+            socket,
+            interval,
+            visibility,
             link,
             auth_state: AuthState::Unknown,
-            task: None,
+            user: None,
+            perms: Perms::default(),
+            session_expired: false,
+            login_to_edit: false,
+            notice: None,
+            prefs: Prefs::default(),
+            cache: Cache::default(),
+            outbox: Outbox::default(),
+            live: Live::default(),
             scene: Scene::Null,
             temp: Temp::default(),
         }
     }
-
-    pub fn render_template(&self, template: &impl Template) -> Html<Self> {
-        VNode::VRef(
-            Node::from_html(
-                &template.render().unwrap()
-                ).unwrap()
-            )
-    }
 }
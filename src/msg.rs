@@ -2,33 +2,57 @@
 #![allow(unused_macros)]
 
 use yew::prelude::*;
-use yew::services::fetch::*;
-use yew::format::Json;
-use yew::format::nothing::*;
-use failure::Error;
 use serde::{Serialize};
 use crate::model::*;
 use crate::views::*;
 use crate::domain::*;
 use crate::authservice::*;
-use stdweb::js;
-use stdweb::unstable::TryInto;
+use crate::liststate::*;
+use crate::listwindow::*;
+use crate::services::*;
+use crate::prefs::*;
+use crate::drafts::*;
+use crate::cache::*;
+use crate::outbox::*;
+use crate::live::*;
+use crate::config::LiveConfig;
+use crate::contract::{ self, Endpoint };
 
 pub enum Msg {
     Null,
     Error(String),
+    Retry,
     Init,
     AuthReady(AuthState),
+    RefreshToken,
+    TokenRefreshed(AuthState),
+    Unauthorized((ApiRequest,Callback<ApiResponse>)),
+    SetUser(Option<User>),
     Route,
     Login,
+    LoginAs(String),
     Logout,
     ChangeScene(Scene),
     RefreshScene,
-    GetProjects,
-    GetUsers,
-    GetWorkers,
+    SetListFilter(String),
+    SortList(SortKey),
+    ToggleColumn(Column),
+    ToggleSection(Section),
+    ScrollList(usize),
+    SetInput(String),
+    GetProjects(usize),
+    GetUsers(usize),
+    ViewUsers(Page<User>),
+    PostViewUsers(Vec<Role>),
+    GrantRole((UserId,RoleId)),
+    RevokeRole((UserId,RoleId)),
+    LinkWorker((UserId,Option<WorkerId>)),
+    PostLinkWorker((UserId,Option<WorkerId>)),
+    GetWorkers(usize),
+    GetPage(usize),
     GetProject(ProjectId),
     GetWorker(WorkerId),
+    GetMyTasks,
     GetTask(TaskId),
     AddProject(ProjectName),
     AddTask((TaskName,ProjectId)),
@@ -38,8 +62,47 @@ pub enum Msg {
     PostViewAssignTask(Vec<ListWorker>),
     AssignTask((TaskId,WorkerId)),
     UnassignTask(TaskId),
+    Revalidated(Scene),
+    RevalidateFailed(String),
+    Saved,
+    Created((i32,i32)),
+    Online(bool),
+    Queue(Queued),
+    Replay,
+    Replayed(Answer),
+    Rejected((String,Undo)),
+    Failed(String),
+    Stale((Change,Option<Undo>)),
+    Overwrite(Change),
+    DismissNotice,
+    Connect,
+    Connected(bool),
+    Event(Event),
+    Poll,
+    Polled((Scene,u32)),
 }
- 
+
+impl Msg {
+    // Guests get the read-only scenes (see Perms::guest); these get them a
+    // prompt to log in instead.
+    fn needs_login(&self) -> bool {
+        matches!(self,
+            Msg::GetUsers(_) |
+            Msg::GrantRole(_) |
+            Msg::RevokeRole(_) |
+            Msg::LinkWorker(_) |
+            Msg::GetMyTasks |
+            Msg::AddProject(_) |
+            Msg::AddTask(_) |
+            Msg::AddWorker(_) |
+            Msg::PreViewAssignTask(_) |
+            Msg::AssignTask(_) |
+            Msg::UnassignTask(_) |
+            Msg::Overwrite(_)
+            )
+    }
+}
+
 pub fn parse_with_default<T>(s: &str, d: T)
     -> T
     where T: std::str::FromStr
@@ -50,370 +113,940 @@ pub fn parse_with_default<T>(s: &str, d: T)
     }
 }
 
+// `$do` must take the endpoint's response type. `$fail` gets what went
+// wrong, and defaults to showing it as an error scene; anything that
+// isn't loading a scene should pass Msg::Failed instead.
 macro_rules! fetch(
-    ($self: ident, $req: ident, $do: expr) => {
-        let callback = $self.link.send_back(
-            move |rsp: Response<Json<Result<Result<_,String>,Error>>>| {
-                let (meta, Json(body)) = rsp.into_parts();
-                if meta.status.is_success() {
-                    match body {
+    ($model: ident, $endpoint: ty, $req: expr, $do: expr) => {
+        fetch!($model, $endpoint, $req, $do, Msg::Error);
+    };
+    ($model: ident, $endpoint: ty, $req: expr, $do: expr, $fail: expr) => {
+        let callback = $model.link.send_back(
+            move |rsp: ApiResponse| {
+                if rsp.is_success() {
+                    match serde_json::from_str::<Result<<$endpoint as Endpoint>::Response,String>>(&rsp.body) {
                         Ok(Ok(s))  => {
                             $do(s)
                         }
                         Ok(Err(s)) => {
-                            Msg::Error(s)
+                            $fail(s)
                         }
                         Err(_) => { 
-                            Msg::Error("JSON error?".into())
+                            $fail("JSON error?".into())
                         }
                     }
                 }
                 else {
-                    Msg::Error("Not 200".into())
+                    $fail("Not 200".into())
                 }
             }
         );
 
-        $self.task = Some($self.fetcher.fetch(
-            $req,
-            callback,
-        ));
+        $model.send($req, callback);
     }
 );
 
-trait Authable
-{
-    fn add_auth(&mut self, auth: &AuthState) -> &mut Self;
-}
-
-impl Authable for http::request::Builder
-{
-    fn add_auth(&mut self, auth: &AuthState) -> &mut Self
-    {
-        match auth {
-            AuthState::Yes(user) => {
-                self.header("Authorization", format!("Bearer {}", user.token))
+// Like `fetch!`, for scenes the cache may already have. A cached copy is
+// shown at once and the response replaces it when it arrives (see
+// Msg::Revalidated); if that fails, the cached copy stays. `$scene` turns
+// the response into the scene, or None if there's nothing there, in which
+// case `$missing` is sent.
+macro_rules! fetch_cached(
+    ($model: ident, $endpoint: ty, $req: expr, $cached: expr, $scene: expr, $missing: expr) => {
+        match $cached {
+            Some(cached) => {
+                $model.link.send_self(Msg::ChangeScene(cached));
+                fetch!($model, $endpoint, $req, move |rsp| {
+                    $scene(rsp).map_or_else(|| $missing, Msg::Revalidated)
+                }, Msg::RevalidateFailed);
             }
-            _ => {
-                self
+            None => {
+                fetch!($model, $endpoint, $req, move |rsp| {
+                    $scene(rsp).map_or_else(|| $missing, Msg::ChangeScene)
+                });
             }
         }
     }
-}
+);
+
+// Like `fetch!`, for Msg::Poll. The request runs alongside any other,
+// and `$scene` turns the response into the scene it's for, or None,
+// which goes to Msg::Polled. A failed poll waits for the next one.
+macro_rules! poll(
+    ($model: ident, $endpoint: ty, $req: expr, $scene: expr) => {
+        let edits = $model.cache.edits();
+        let callback = $model.link.send_back(move |rsp: ApiResponse| {
+            match serde_json::from_str::<Result<<$endpoint as Endpoint>::Response,String>>(&rsp.body) {
+                Ok(Ok(s)) if rsp.is_success() => {
+                    $scene(s).map_or(Msg::Null, |scene| Msg::Polled((scene, edits)))
+                }
+                _ => Msg::Null,
+            }
+        });
+
+        $model.http.poll($req, callback);
+    }
+);
 
 trait Requester {
-    fn get(&self, action: &str) -> Request<Nothing>;
-    fn post<'a, T: Serialize>(&self, action: &str, body: &'a T) 
-        -> Request<Json<&'a T>>;
+    fn get<E: Endpoint<Params = ()>>(&self, args: &[&dyn std::fmt::Display]) -> ApiRequest;
+    fn get_page<E: Endpoint<Params = ()>>(&self, page: usize, list: &ListState) -> ApiRequest;
+    fn post<E: Endpoint>(&self, params: &E::Params) -> ApiRequest;
+    fn send(&mut self, req: ApiRequest, callback: Callback<ApiResponse>);
 }
 
 impl Requester for Model {
-    fn get(&self, action: &str) -> Request<Nothing> {
-        Request::get(format!("{}/api/{}", remote_host(), action))
-            .add_auth(&self.auth_state)
-            .body(Nothing)
-            .unwrap()
-    }
-
-    fn post<'a, T: Serialize>(&self, action: &str, body: &'a T) 
-        -> Request<Json<&'a T>> 
-    {
-        Request::post(format!("{}/api/{}", remote_host(), action))
-            .header("Content-Type", "application/json")
-            .add_auth(&self.auth_state)
-            .body(Json(body))
-            .expect("Failed to build request")
+    fn get<E: Endpoint<Params = ()>>(&self, args: &[&dyn std::fmt::Display]) -> ApiRequest {
+        ApiRequest {
+            method: E::METHOD,
+            action: E::action(args),
+            body: None,
+            token: self.auth_state.token(),
+        }
+    }
+
+    // Paged lists are filtered and sorted by the server (see contract.rs).
+    fn get_page<E: Endpoint<Params = ()>>(&self, page: usize, list: &ListState) -> ApiRequest {
+        let mut req = self.get::<E>(&[&(page * PAGE_SIZE), &PAGE_SIZE]);
+        let query = list.search_query();
+        if !query.is_empty() {
+            req.action = format!("{}?{}", req.action, query);
+        }
+        req
+    }
+
+    fn post<E: Endpoint>(&self, params: &E::Params) -> ApiRequest {
+        ApiRequest {
+            method: E::METHOD,
+            action: E::action(&[]),
+            body: Some(serde_json::to_string(params).expect("Failed to build request")),
+            token: self.auth_state.token(),
+        }
+    }
+
+    // A 401 on a request with a token refreshes the token and sends it
+    // again (see Msg::Unauthorized); anything else goes to `callback`.
+    fn send(&mut self, req: ApiRequest, callback: Callback<ApiResponse>) {
+        // Like the GET in flight, a waiting one is superseded. Changes
+        // run alongside (see Http::fetch), so theirs keep waiting.
+        if req.method != contract::Method::Post {
+            self.temp.retry = None;
+        }
+
+        let link = self.link.clone();
+        let retry = req.clone();
+        let was_online = self.online;
+        let guarded = Callback::from(move |rsp: ApiResponse| {
+            // The browser's events miss the server going away, or coming
+            // back.
+            if rsp.is_offline() == was_online {
+                link.send_self(Msg::Online(!was_online));
+            }
+
+            if rsp.status == 401 && retry.token.is_some() {
+                link.send_self(Msg::Unauthorized((retry.clone(), callback.clone())));
+            }
+            else {
+                callback.emit(rsp);
+            }
+        });
+
+        self.http.fetch(req, guarded);
     }
 }
 
-fn remote_host() -> String
-{
-    js!( return api_url; )
-    .try_into()
-    .unwrap()
+// Tokens are refreshed this long before they expire.
+const REFRESH_MARGIN_MS: f64 = 60_000.0;
+
+fn schedule_refresh(model: &mut Model) {
+    if let AuthState::Yes(AuthUser { expires_at: Some(expires_at), .. }) = &model.auth_state {
+        let delay = (expires_at - REFRESH_MARGIN_MS - model.timer.now()).max(0.0);
+        let callback = model.link.send_back(|_| Msg::RefreshToken);
+        model.timer.schedule("refresh", delay as u64, callback);
+    }
 }
 
 macro_rules! log(
-    ($s: literal $(,$x: expr)*) => { 
-        let msg = format!($s $(, $x)*);
-        js!{ console.log(@{msg}); }; 
+    ($model: ident, $s: literal $(,$x: expr)*) => { 
+        $model.logger.log(&format!($s $(, $x)*));
     };
 );
 
-fn route(path: Vec<String>) -> Msg {
-    let slices : Vec<&str> = path.iter().map(|s| s.as_ref()).collect();
-    match slices.as_slice() {
-        []             => Msg::Null,
-        ["projects"]   => Msg::GetProjects,
-        ["workers"]    => Msg::GetWorkers,
-        ["users"]      => Msg::GetUsers,
-        ["project", n] => Msg::GetProject(parse_with_default(n, 0)),
-        ["worker", n]  => Msg::GetWorker(parse_with_default(n, 0)),
-        ["task", n]    => Msg::GetTask(parse_with_default(n, 0)),
-        ["assign", n]  => Msg::PreViewAssignTask(parse_with_default(n, 0)),
-        _              => Msg::Null,
+// Shows `change` before the server has answered, and posts `params` to
+// make it. If the server says no, the change is taken back. If it can't be
+// reached, or changes made offline are still waiting, it goes in the
+// outbox (see outbox.rs). If someone else changed it first, the user gets
+// to choose (see Msg::Stale). Nothing is fetched again afterwards, so
+// saving never gets in the way of moving on to another scene.
+fn save_optimistically<E: Endpoint>(model: &mut Model, params: &E::Params, change: Change) {
+    let undo = model.cache.undo(&change);
+    model.cache.apply(&change);
+    model.cache.show(&mut model.scene, &change);
+
+    let req = model.post::<E>(params);
+    let queued = Queued::new(&req, change);
+    if !model.online || !model.outbox.is_empty() {
+        model.link.send_self(Msg::Queue(queued));
+        return;
     }
+
+    let callback = model.link.send_back(move |rsp: ApiResponse| {
+        match Answer::from(&rsp) {
+            Answer::Saved(id)   => saved(&queued.change, id),
+            Answer::Rejected(s) => Msg::Rejected((s, undo.clone())),
+            Answer::Stale(_)    => Msg::Stale((queued.change.clone(), Some(undo.clone()))),
+            Answer::Offline     => Msg::Queue(queued.clone()),
+            Answer::Retry       => Msg::Queue(queued.clone()),
+        }
+    });
+    model.send(req, callback);
 }
 
-fn register_add_project_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::AddProject);
-    let cb = move |x: ProjectName| cb.emit(x); 
-    
-    js!(
-        add_project = function(x) {
-            console.log("add_project(" + x + ")");
-            document.getElementById("add_project_name").value = "";
-            @{cb}(x);
-        };
-    );
+// The filter and sort for the list at `path`, as Msg::ChangeScene will
+// show it. Staying on the same path (refresh, deep link) keeps the list
+// state from the URL; going somewhere new starts fresh, sorted the way
+// that kind of list was last sorted.
+fn list_for(model: &Model, path: &str) -> ListState {
+    if model.loc.get_hash_path().join("/") == path {
+        return ListState::from_query(&model.loc.get_hash_query());
+    }
+    let mut list = ListState::default();
+    if let Some(saved) = model.prefs.sort_for(path) {
+        list.sort = saved.sort;
+        list.desc = saved.desc;
+    }
+    list
 }
 
-fn register_add_worker_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::AddWorker);
-    let cb = move |x: WorkerName| cb.emit(x); 
-    
-    js!(
-        add_worker = function(x) {
-            console.log("add_worker(" + x + ")");
-            document.getElementById("add_worker_name").value = "";
-            @{cb}(x);
-        };
-    );
+// A page past the last (an old link, or fewer matches than before) is
+// fetched again as the last one.
+fn past_the_end<T>(page: &Page<T>) -> Option<usize> {
+    Some(page.count() - 1).filter(|&last| page.index() > last)
 }
 
-fn register_add_task_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::AddTask);
-    let cb = move |x: TaskName, y: ProjectId| cb.emit((x,y)); 
-    
-    js!(
-        add_task = function(x,y) {
-            console.log("add_task(" + x + "," + y + ")");
-            document.getElementById("add_task_name").value = "";
-            @{cb}(x,y);
-        };
-    );
+// Paged lists have to be fetched again for a new filter or sort, from
+// the first page. Off it, Msg::Route does that once the URL changes.
+fn refilter(model: &mut Model) {
+    if let Some(page) = model.scene.page_index() {
+        if page == 0 {
+            model.link.send_self(Msg::GetPage(0));
+        }
+        if let Some(list) = model.scene.list_mut() {
+            list.page = 0;
+        }
+    }
 }
 
-fn register_get_project_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::GetProject);
-    let cb = move |x: ProjectId| cb.emit(x); 
-    
-    js!(
-        get_project = function(x) {
-            console.log("get_project(" + x + ")");
-            @{cb}(x);
-        };
-    );
+// Anything added gets its real id.
+fn saved(change: &Change, id: Option<i32>) -> Msg {
+    match (change.unsaved_id(), id) {
+        (Some(unsaved), Some(id)) => Msg::Created((unsaved, id)),
+        _                         => Msg::Saved,
+    }
 }
 
-fn register_get_worker_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::GetWorker);
-    let cb = move |x: WorkerId| cb.emit(x); 
-    
-    js!(
-        get_worker = function(x) {
-            console.log("get_worker(" + x + ")");
-            @{cb}(x);
-        };
-    );
+// Submitting a form is the end of its draft.
+fn clear_input(model: &mut Model) {
+    if let Some(input) = model.scene.input_mut() {
+        input.clear();
+        save_draft(&mut *model.storage, &model.scene.hash_path(), "");
+    }
 }
 
-fn register_get_task_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::GetTask);
-    let cb = move |x: TaskId| cb.emit(x); 
-    
-    js!(
-        get_task = function(x) {
-            console.log("get_task(" + x + ")");
-            @{cb}(x);
-        };
-    );
+fn projects_scene(projects: Page<ListProject>) -> Scene {
+    Scene::Projects(
+        ProjectsView{
+            projects,
+            list: ListState::default(),
+            input: String::new(),
+            perms: Perms::default(),
+            layout: Layout::default(),
+        }
+    )
 }
 
-fn register_view_assign_task_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::PreViewAssignTask);
-    let cb = move |x: TaskId| cb.emit(x); 
-    
-    js!(
-        view_assign_task = function(x) {
-            console.log("view_assign_task(" + x + ")");
-            @{cb}(x);
-        };
-    );
+fn workers_scene(workers: Page<ListWorker>) -> Scene {
+    Scene::Workers(
+        WorkersView{
+            workers,
+            list: ListState::default(),
+            input: String::new(),
+            perms: Perms::default(),
+            layout: Layout::default(),
+        }
+    )
 }
 
-fn register_assign_task_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::AssignTask);
-    let cb = move |x: TaskId, y: WorkerId| cb.emit((x,y)); 
-    
-    js!(
-        assign_task = function(x,y) {
-            console.log("assign_task(" + x + "," + y + ")");
-            @{cb}(x,y);
-        };
-    );
+fn project_scene(project: ProjectDetails) -> Scene {
+    Scene::ProjectDetails(
+        ProjectDetailsView{
+            project,
+            list: ListState::default(),
+            window: ListWindow::default(),
+            input: String::new(),
+            perms: Perms::default(),
+            layout: Layout::default(),
+        }
+    )
 }
 
-fn register_unassign_task_js(model: &mut Model) {
-    let cb = model.link.send_back(Msg::UnassignTask);
-    let cb = move |x: TaskId| cb.emit(x); 
-    
-    js!(
-        unassign_task = function(x) {
-            console.log("unassign_task(" + x + ")");
-            @{cb}(x);
-        };
-    );
+fn worker_scene(worker: WorkerDetails) -> Scene {
+    Scene::WorkerDetails(
+        WorkerDetailsView{
+            worker,
+            list: ListState::default(),
+            window: ListWindow::default(),
+            input: String::new(),
+            perms: Perms::default(),
+            layout: Layout::default(),
+        }
+    )
+}
+
+fn my_tasks_scene(worker: WorkerDetails) -> Scene {
+    Scene::MyTasks(
+        MyTasksView{
+            worker,
+            list: ListState::default(),
+            window: ListWindow::default(),
+            layout: Layout::default(),
+        }
+    )
+}
+
+fn task_scene(task: TaskDetails) -> Scene {
+    Scene::TaskDetails(
+        TaskDetailsView{ task, perms: Perms::default(), me: None }
+    )
+}
+
+fn conflict_scene(change: Change, theirs: Theirs) -> Msg {
+    Msg::ChangeScene(
+        Scene::Conflict(
+            ConflictView{ change, theirs, perms: Perms::default() }
+        )
+    )
 }
-            
-fn register_msg_js(model: &mut Model) {
-    register_add_project_js(model);
-    register_add_worker_js(model);
-    register_add_task_js(model);
-    register_get_project_js(model);
-    register_get_worker_js(model);
-    register_get_task_js(model);
-    register_view_assign_task_js(model);
-    register_assign_task_js(model);
-    register_unassign_task_js(model);
+
+fn route(path: Vec<String>, list: ListState) -> Msg {
+    let slices : Vec<&str> = path.iter().map(|s| s.as_ref()).collect();
+    match slices.as_slice() {
+        []             => Msg::ChangeScene(Scene::Null),
+        ["projects"]   => Msg::GetProjects(list.page),
+        ["workers"]    => Msg::GetWorkers(list.page),
+        ["users"]      => Msg::GetUsers(list.page),
+        ["project", n] => Msg::GetProject(parse_with_default(n, 0)),
+        ["worker", n]  => Msg::GetWorker(parse_with_default(n, 0)),
+        ["me"]         => Msg::GetMyTasks,
+        ["task", n]    => Msg::GetTask(parse_with_default(n, 0)),
+        ["assign", n]  => Msg::PreViewAssignTask(parse_with_default(n, 0)),
+        _              => Msg::Null,
+    }
 }
 
 pub fn update(model: &mut Model, msg: Msg) -> ShouldRender {
+    if model.is_guest() && msg.needs_login() {
+        log!(model, "Guest needs to log in");
+        model.login_to_edit = true;
+        return true;
+    }
+
     match msg {
         Msg::Null => {
-            log!("Msg::Null");
+            log!(model, "Msg::Null");
             // Do absolutely nothing.
         }
         Msg::Error(s) => {
-            log!("Msg::Error({})", s);
-            model.task = None;
+            log!(model, "Msg::Error({})", s);
+            model.http.cancel();
+            model.scene = Scene::Error(
+                ErrorView {
+                    message: s,
+                    path: model.loc.get_hash_path().join("/"),
+                }
+            );
+        }
+        Msg::Retry => {
+            log!(model, "Msg::Retry");
+            let path = model.loc.get_hash_path();
+            let list = ListState::from_query(&model.loc.get_hash_query());
+            model.link.send_self(route(path, list));
         }
         Msg::Init => {
-            log!("Msg::Init");
-            register_msg_js(model);
+            log!(model, "Msg::Init");
+            model.prefs = Prefs::load(&*model.storage);
+            model.outbox = Outbox::load(&*model.storage);
+            model.cache.skip_unsaved(model.outbox.lowest_unsaved());
+            model.network.init(model.link.send_back(Msg::Online));
+            match model.config.live {
+                LiveConfig::Off                  => (),
+                LiveConfig::WebSocket            => model.link.send_self(Msg::Connect),
+                LiveConfig::Poll { every_secs }  => {
+                    let callback = model.link.send_back(|_| Msg::Poll);
+                    model.interval.start(every_secs as u64 * 1000, callback);
+                }
+            }
             model.loc.init(model.link.send_back(|_| Msg::Route));
             model.auth.init(model.link.send_back(Msg::AuthReady));
         }
         Msg::Login => {
-            log!("Msg::Login");
+            log!(model, "Msg::Login");
             model.auth.login();
         }
+        Msg::LoginAs(account) => {
+            log!(model, "Msg::LoginAs({})", account);
+            model.auth.login_as(&account);
+        }
         Msg::Logout => {
-            log!("Msg::Logout");
+            log!(model, "Msg::Logout");
             model.auth.logout();
         }
         Msg::Route => {
-            log!("Msg::Route");
-            model.link.send_self(
-                route(model.loc.get_hash_path())
-                );
+            log!(model, "Msg::Route");
+            let path = model.loc.get_hash_path();
+            let list = ListState::from_query(&model.loc.get_hash_query());
+
+            let first = !model.temp.routed;
+            model.temp.routed = true;
+            let landing = model.prefs.landing.clone().filter(|_| first && path.is_empty());
+            if let Some(landing) = landing {
+                // Opened with nothing asked for: pick up where the last
+                // visit left off.
+                model.loc.set_hash_path(landing);
+            }
+            // Only the query changed: keep the data, update the list state,
+            // and fetch again only if it asks for a different page.
+            else if path.join("/") == model.scene.hash_path() {
+                if let Some(page) = model.scene.page_index() {
+                    if page != list.page {
+                        model.link.send_self(Msg::GetPage(list.page));
+                    }
+                }
+                if let Some(current) = model.scene.list_mut() {
+                    *current = list;
+                }
+            }
+            else {
+                model.link.send_self(route(path, list));
+            }
         }
         Msg::AuthReady(auth_state) => {
-            log!("Msg::AuthReady");
+            log!(model, "Msg::AuthReady");
             model.auth_state = auth_state;
+            schedule_refresh(model);
+            match model.auth_state {
+                AuthState::Yes(_) => {
+                    model.session_expired = false;
+                    model.login_to_edit = false;
+                    let req = model.get::<contract::GetMe>(&[]);
+                    fetch!(model, contract::GetMe, req, Msg::SetUser);
+                }
+                _ => {
+                    model.link.send_self(Msg::SetUser(None));
+                }
+            }
+        }
+        Msg::RefreshToken => {
+            log!(model, "Msg::RefreshToken");
+            model.auth.refresh(model.link.send_back(Msg::TokenRefreshed));
+        }
+        Msg::TokenRefreshed(auth_state) => {
+            log!(model, "Msg::TokenRefreshed");
+            match auth_state {
+                AuthState::Yes(_) => {
+                    model.auth_state = auth_state;
+                    schedule_refresh(model);
+                    let retries = model.temp.retries.drain(..).chain(model.temp.retry.take());
+                    for (mut req, callback) in retries.collect::<Vec<_>>() {
+                        req.token = model.auth_state.token();
+                        // Not through `send`: a second 401 is an error,
+                        // not another refresh.
+                        model.http.fetch(req, callback);
+                    }
+                }
+                _ => {
+                    // A replayed change waiting on the refresh stays in the
+                    // outbox for next time.
+                    model.temp.retry = None;
+                    model.temp.retries.clear();
+                    model.outbox.sending = false;
+                    model.http.cancel();
+                    model.auth_state = AuthState::No;
+                    model.session_expired = true;
+                    model.link.send_self(Msg::SetUser(None));
+                }
+            }
+        }
+        Msg::Unauthorized((req, callback)) => {
+            log!(model, "Msg::Unauthorized({})", req.action);
+            let refreshing = model.temp.retry.is_some() || !model.temp.retries.is_empty();
+            if req.method == contract::Method::Post {
+                model.temp.retries.push((req, callback));
+            }
+            else {
+                model.temp.retry = Some((req, callback));
+            }
+            if !refreshing {
+                model.link.send_self(Msg::RefreshToken);
+            }
+        }
+        Msg::SetUser(user) => {
+            log!(model, "Msg::SetUser({:?})", user.as_ref().map(|u| &u.id));
+            model.perms = match &user {
+                Some(user)                => user.perms(),
+                None if model.is_guest() => Perms::guest(),
+                None                      => Perms::default(),
+            };
+            model.user = user;
+            let me = model.user.as_ref().and_then(|user| user.worker_id);
+            model.scene.set_user(&model.perms, me);
             model.link.send_self(Msg::Route);
+            // Changes left waiting by the last visit can go now.
+            model.link.send_self(Msg::Replay);
         }
-        Msg::ChangeScene(scene) => {
-            log!("Msg::ChangeScene");
-            model.loc.set_hash_path(scene.hash_path());
+        Msg::ChangeScene(mut scene) => {
+            log!(model, "Msg::ChangeScene");
+            model.login_to_edit = false;
+            if scene.hash_path() != model.scene.hash_path() {
+                model.notice = None;
+            }
+
+            let list = list_for(model, &scene.hash_path());
+            if let Some(current) = scene.list_mut() {
+                *current = list;
+            }
+
+            // The page actually fetched wins over whatever the URL said.
+            if let Some(page) = scene.page_index() {
+                if let Some(list) = scene.list_mut() {
+                    list.page = page;
+                }
+            }
+
+            // A refresh of the same view keeps its scroll position.
+            if scene.hash_path() == model.scene.hash_path() {
+                if let (Some(window), Some(current)) = (scene.window_mut(), model.scene.window()) {
+                    *window = *current;
+                }
+            }
+
+            // Views are built in fetch callbacks, which can't see who is
+            // logged in.
+            scene.set_user(&model.perms, model.user.as_ref().and_then(|user| user.worker_id));
+            if let Some(layout) = scene.layout_mut() {
+                *layout = model.prefs.layout.clone();
+            }
+            let path = scene.hash_path();
+            if let Some(input) = scene.input_mut() {
+                *input = restore_draft(&*model.storage, &path).unwrap_or_default();
+            }
+
+            let landing = Some(scene.hash_path());
+            if !matches!(scene, Scene::Null | Scene::Error(_)) && model.prefs.landing != landing {
+                model.prefs.landing = landing;
+                model.prefs.save(&mut *model.storage);
+            }
+
+            model.cache.remember(&scene);
+            model.loc.set_hash_path(scene.location());
             model.scene = scene;
         }
+        Msg::Revalidated(scene) => {
+            log!(model, "Msg::Revalidated");
+            // Still looking at the cached copy: swap in the fresh one.
+            if scene.hash_path() == model.scene.hash_path() {
+                model.link.send_self(Msg::ChangeScene(scene));
+            }
+            else {
+                model.cache.remember(&scene);
+            }
+        }
+        Msg::RevalidateFailed(s) => {
+            log!(model, "Msg::RevalidateFailed({})", s);
+            // The cached copy is still on screen, and better than nothing.
+        }
+        Msg::Saved => {
+            log!(model, "Msg::Saved");
+        }
+        Msg::Created((unsaved, id)) => {
+            log!(model, "Msg::Created({} is {})", unsaved, id);
+            let change = Change::Created { unsaved, id };
+            model.cache.apply(&change);
+            model.cache.show(&mut model.scene, &change);
+            model.outbox.created(&mut *model.storage, unsaved, id);
+        }
+        Msg::Online(online) => {
+            log!(model, "Msg::Online({})", online);
+            model.online = online;
+            if online {
+                model.link.send_self(Msg::Replay);
+            }
+        }
+        Msg::Queue(queued) => {
+            log!(model, "Msg::Queue({})", queued.action);
+            model.outbox.push(&mut *model.storage, queued);
+            model.link.send_self(Msg::Replay);
+        }
+        Msg::Replay => {
+            if !model.online || model.outbox.sending || model.user.is_none() {
+                return true;
+            }
+            log!(model, "Msg::Replay({} waiting)", model.outbox.len());
+            let req = model.outbox.front().map(|queued| queued.request(model.auth_state.token()));
+            match req {
+                Some(req) => {
+                    model.outbox.sending = true;
+                    let callback = model.link.send_back(|rsp: ApiResponse| Msg::Replayed(Answer::from(&rsp)));
+                    model.send(req, callback);
+                }
+                // All sent. Anything turned down may have left the cache
+                // showing what the server doesn't have, so start over.
+                None if !model.outbox.conflicts.is_empty() => {
+                    model.notice = Some(format!("Some changes made offline didn't save: {}",
+                        model.outbox.conflicts.join("; ")));
+                    model.outbox.conflicts.clear();
+                    model.cache = Cache::default();
+                    model.link.send_self(Msg::RefreshScene);
+                }
+                None => (),
+            }
+        }
+        Msg::Replayed(answer) => {
+            log!(model, "Msg::Replayed({:?})", answer);
+            model.outbox.sending = false;
+            match answer {
+                Answer::Offline => {
+                    model.online = false;
+                    return true;
+                }
+                // Stays first in line, and goes again after a wait.
+                Answer::Retry => {
+                    let callback = model.link.send_back(|_| Msg::Replay);
+                    model.timer.schedule("replay", RETRY_MS, callback);
+                    return true;
+                }
+                Answer::Saved(id) => {
+                    if let Some(queued) = model.outbox.pop(&mut *model.storage) {
+                        model.link.send_self(saved(&queued.change, id));
+                    }
+                }
+                Answer::Rejected(s) => {
+                    if let Some(queued) = model.outbox.pop(&mut *model.storage) {
+                        model.outbox.conflicts.push(format!("{} ({})", queued.change.describe(), s));
+                    }
+                }
+                Answer::Stale(_) => {
+                    if let Some(queued) = model.outbox.pop(&mut *model.storage) {
+                        model.link.send_self(Msg::Stale((queued.change, None)));
+                    }
+                }
+            }
+            model.link.send_self(Msg::Replay);
+        }
+        // Takes back just that change. Anything else in flight may not be
+        // saved yet, so the scene is only fetched again if it has to be.
+        Msg::Rejected((s, undo)) => {
+            log!(model, "Msg::Rejected({})", s);
+            model.cache.revert(&undo);
+            model.notice = Some(format!("That didn't save: {}", s));
+            if !model.cache.show_undo(&mut model.scene, &undo) {
+                model.link.send_self(Msg::RefreshScene);
+            }
+        }
+        // A change the cache doesn't track didn't go through. Whatever
+        // is on screen is still right, so it stays.
+        Msg::Failed(s) => {
+            log!(model, "Msg::Failed({})", s);
+            model.notice = Some(format!("That didn't save: {}", s));
+        }
+        // Shows what the server has now, next to what the user tried. A
+        // change made offline has nothing to undo; the cache has been
+        // written over with what came back before it's seen again.
+        Msg::Stale((change, undo)) => {
+            log!(model, "Msg::Stale({})", change.describe());
+            if let Some(undo) = &undo {
+                model.cache.revert(undo);
+            }
+            match change {
+                Change::TaskAssigned { task_id, .. } | Change::TaskUnassigned { task_id } => {
+                    let req = model.get::<contract::GetTask>(&[&task_id]);
+                    fetch!(model, contract::GetTask, req, |task: Option<TaskDetails>| {
+                        task.map_or(Msg::Null, |task| conflict_scene(change.clone(), Theirs::Task(task)))
+                    }, Msg::Failed);
+                }
+                Change::TaskAdded { project_id, .. } => {
+                    let req = model.get::<contract::GetProject>(&[&project_id]);
+                    fetch!(model, contract::GetProject, req, |project: Option<ProjectDetails>| {
+                        project.map_or(Msg::Null, |project| conflict_scene(change.clone(), Theirs::Project(project)))
+                    }, Msg::Failed);
+                }
+                // Nothing else has a version to be out of date.
+                _ => {
+                    model.link.send_self(Msg::Rejected(("Changed by someone else".into(), Undo::Refetch)));
+                }
+            }
+        }
+        // Makes the change again, against the version just fetched, from
+        // the scene it was made in.
+        Msg::Overwrite(change) => {
+            log!(model, "Msg::Overwrite({})", change.describe());
+            match change {
+                Change::TaskAssigned { task_id, worker_id } => {
+                    if let Some(task) = model.cache.task(task_id) {
+                        model.link.send_self(Msg::ChangeScene(task_scene(task)));
+                    }
+                    model.link.send_self(Msg::AssignTask((task_id, worker_id)));
+                }
+                Change::TaskUnassigned { task_id } => {
+                    if let Some(task) = model.cache.task(task_id) {
+                        model.link.send_self(Msg::ChangeScene(task_scene(task)));
+                    }
+                    model.link.send_self(Msg::UnassignTask(task_id));
+                }
+                Change::TaskAdded { project_id, task } => {
+                    if let Some(project) = model.cache.project(project_id) {
+                        model.link.send_self(Msg::ChangeScene(project_scene(project)));
+                    }
+                    model.link.send_self(Msg::AddTask((task.name, project_id)));
+                }
+                _ => (),
+            }
+        }
+        Msg::DismissNotice => {
+            model.notice = None;
+        }
+        Msg::Connect => {
+            let url = model.config.events_url();
+            log!(model, "Msg::Connect({})", url);
+            model.live.waiting = false;
+            let on_message = model.link.send_back(|text: String| {
+                serde_json::from_str(&text).map_or(Msg::Null, Msg::Event)
+            });
+            let on_open = model.link.send_back(Msg::Connected);
+            model.socket.connect(&url, on_message, on_open);
+        }
+        Msg::Connected(open) => {
+            log!(model, "Msg::Connected({})", open);
+            if open {
+                if model.live.was_open {
+                    model.link.send_self(Msg::RefreshScene);
+                }
+                model.live = Live { open: true, was_open: true, ..Live::default() };
+            }
+            else if !model.live.waiting {
+                model.live.open = false;
+                model.live.waiting = true;
+                let delay = model.live.backoff();
+                model.live.retries += 1;
+                model.timer.schedule("reconnect", delay, model.link.send_back(|_| Msg::Connect));
+            }
+        }
+        // Fetches what's on screen again, as Msg::RefreshScene would,
+        // unless no one can see it. Changes still waiting to be saved
+        // would be missing from the answer, so nothing is fetched until
+        // they're in.
+        Msg::Poll => {
+            if !model.visibility.is_visible() || !model.outbox.is_empty() {
+                return false;
+            }
+            match &model.scene {
+                Scene::Projects(view) => {
+                    let req = model.get_page::<contract::GetProjectsPage>(view.projects.index(), &view.list);
+                    poll!(model, contract::GetProjectsPage, req, |projects| Some(projects_scene(projects)));
+                }
+                Scene::Workers(view) => {
+                    let req = model.get_page::<contract::GetWorkersPage>(view.workers.index(), &view.list);
+                    poll!(model, contract::GetWorkersPage, req, |workers| Some(workers_scene(workers)));
+                }
+                Scene::ProjectDetails(view) => {
+                    let req = model.get::<contract::GetProject>(&[&view.project.id]);
+                    poll!(model, contract::GetProject, req, |project: Option<ProjectDetails>| project.map(project_scene));
+                }
+                Scene::WorkerDetails(view) => {
+                    let req = model.get::<contract::GetWorker>(&[&view.worker.id]);
+                    poll!(model, contract::GetWorker, req, |worker: Option<WorkerDetails>| worker.map(worker_scene));
+                }
+                Scene::MyTasks(view) => {
+                    let req = model.get::<contract::GetWorker>(&[&view.worker.id]);
+                    poll!(model, contract::GetWorker, req, |worker: Option<WorkerDetails>| worker.map(my_tasks_scene));
+                }
+                Scene::TaskDetails(view) => {
+                    let req = model.get::<contract::GetTask>(&[&view.task.id]);
+                    poll!(model, contract::GetTask, req, |task: Option<TaskDetails>| task.map(task_scene));
+                }
+                // Take more than one call to build, or have nothing to poll.
+                _ => (),
+            }
+            return false;
+        }
+        // Shown if it's for the scene still on screen, no change has been
+        // made since it was asked for, and it's different.
+        Msg::Polled((scene, edits)) => {
+            let current = scene.hash_path() == model.scene.hash_path() && edits == model.cache.edits();
+            if !current || scene.same_data(&model.scene) {
+                return false;
+            }
+            log!(model, "Msg::Polled({})", scene.hash_path());
+            model.link.send_self(Msg::ChangeScene(scene));
+        }
+        Msg::Event(event) => {
+            log!(model, "Msg::Event({:?})", event.id());
+            model.cache.receive(&event);
+            model.cache.show_event(&mut model.scene, &event);
+            if let Event::Deleted(id) = event {
+                if id.hash_path() == model.scene.hash_path() {
+                    model.notice = Some("Someone else deleted this.".into());
+                }
+            }
+        }
+        Msg::SetListFilter(filter) => {
+            if let Some(list) = model.scene.list_mut() {
+                list.filter = filter;
+            }
+            refilter(model);
+            model.loc.set_hash_path(model.scene.location());
+        }
+        Msg::ScrollList(scroll_top) => {
+            // Fires on every scroll event; skip rendering unless the
+            // visible rows actually changed.
+            return model.scene.scroll_list(scroll_top);
+        }
+        Msg::SetInput(text) => {
+            let path = model.scene.hash_path();
+            if let Some(input) = model.scene.input_mut() {
+                save_draft(&mut *model.storage, &path, &text);
+                *input = text;
+            }
+        }
+        Msg::SortList(key) => {
+            log!(model, "Msg::SortList({})", key.as_str());
+            let path = model.scene.hash_path();
+            if let Some(list) = model.scene.list_mut() {
+                list.toggle_sort(key);
+                model.prefs.set_sort(&path, list);
+                model.prefs.save(&mut *model.storage);
+            }
+            refilter(model);
+            model.loc.set_hash_path(model.scene.location());
+        }
+        Msg::ToggleColumn(column) => {
+            log!(model, "Msg::ToggleColumn({:?})", column);
+            model.prefs.layout.toggle_column(column);
+            model.prefs.save(&mut *model.storage);
+            if let Some(layout) = model.scene.layout_mut() {
+                *layout = model.prefs.layout.clone();
+            }
+        }
+        Msg::ToggleSection(section) => {
+            log!(model, "Msg::ToggleSection({:?})", section);
+            model.prefs.layout.toggle_section(section);
+            model.prefs.save(&mut *model.storage);
+            if let Some(layout) = model.scene.layout_mut() {
+                *layout = model.prefs.layout.clone();
+            }
+        }
         Msg::RefreshScene => {
             let msg = match &model.scene {
                 Scene::Null 
                     => Msg::Null,
-                Scene::Projects(_) 
-                    => Msg::GetProjects,
-                Scene::Workers(_)
-                    => Msg::GetWorkers,
-                Scene::Users(_)
-                    => Msg::GetUsers,
+                Scene::Error(_)
+                    => Msg::Retry,
+                Scene::Projects(view) 
+                    => Msg::GetProjects(view.projects.index()),
+                Scene::Workers(view)
+                    => Msg::GetWorkers(view.workers.index()),
+                Scene::Users(view)
+                    => Msg::GetUsers(view.users.index()),
                 Scene::ProjectDetails(view) 
                     => Msg::GetProject(view.project.id),
                 Scene::WorkerDetails(view) 
                     => Msg::GetWorker(view.worker.id),
+                Scene::MyTasks(_)
+                    => Msg::GetMyTasks,
                 Scene::TaskDetails(view) 
                     => Msg::GetTask(view.task.id),
                 Scene::AssignTask(view) 
                     => Msg::ViewAssignTask(view.task.clone()),
+                Scene::Conflict(view)
+                    => Msg::Stale((view.change.clone(), None)),
             };
 
             model.link.send_self(msg);
         }
-        Msg::GetProjects => {
-            log!("Msg::GetProjects");
-            let req = model.get("get_projects");
-            fetch!(model, req, |projects: Vec<ListProject>| {
-                Msg::ChangeScene(
-                    Scene::Projects(
-                        ProjectsView{projects}
-                    )
-                )
-            });
+        Msg::GetPage(page) => {
+            log!(model, "Msg::GetPage({})", page);
+            let msg = match &model.scene {
+                Scene::Projects(_) => Msg::GetProjects(page),
+                Scene::Workers(_)  => Msg::GetWorkers(page),
+                Scene::Users(_)    => Msg::GetUsers(page),
+                _                  => Msg::Null,
+            };
+
+            model.link.send_self(msg);
         }
-        Msg::GetWorkers => {
-            log!("Msg::GetWorkers");
-            let req = model.get("get_workers");
-            fetch!(model, req, |workers: Vec<ListWorker>| {
-                Msg::ChangeScene(
-                    Scene::Workers(
-                        WorkersView{workers}
-                    )
-                )
+        Msg::GetProjects(page) => {
+            log!(model, "Msg::GetProjects({})", page);
+            let req = model.get_page::<contract::GetProjectsPage>(page, &list_for(model, "projects"));
+            fetch!(model, contract::GetProjectsPage, req, |projects: Page<ListProject>| {
+                match past_the_end(&projects) {
+                    Some(last) => Msg::GetProjects(last),
+                    None       => Msg::ChangeScene(projects_scene(projects)),
+                }
             });
         }
-        Msg::GetProject(id) => {
-            log!("Msg::GetProject({:?})", id);
-            let req = model.get(&format!("get_project/{}", id));
-            fetch!(model, req, |project: Option<ProjectDetails>| {
-                if let Some(project) = project {
-                    Msg::ChangeScene(
-                        Scene::ProjectDetails(
-                            ProjectDetailsView{project}
-                        )
-                    )
-                }
-                else {
-                    Msg::Null
+        Msg::GetWorkers(page) => {
+            log!(model, "Msg::GetWorkers({})", page);
+            let req = model.get_page::<contract::GetWorkersPage>(page, &list_for(model, "workers"));
+            fetch!(model, contract::GetWorkersPage, req, |workers: Page<ListWorker>| {
+                match past_the_end(&workers) {
+                    Some(last) => Msg::GetWorkers(last),
+                    None       => Msg::ChangeScene(workers_scene(workers)),
                 }
             });
         }
+        Msg::GetProject(id) => {
+            log!(model, "Msg::GetProject({:?})", id);
+            let req = model.get::<contract::GetProject>(&[&id]);
+            let cached = model.cache.project(id).map(project_scene);
+            fetch_cached!(model, contract::GetProject, req, cached, |project: Option<ProjectDetails>| {
+                project.map(project_scene)
+            }, Msg::Null);
+        }
         Msg::GetWorker(id) => {
-            log!("Msg::GetWorker({:?})", id);
-            let req = model.get(&format!("get_worker/{}", id));
-            fetch!(model, req, |worker: Option<WorkerDetails>| {
-                if let Some(worker) = worker {
-                    Msg::ChangeScene(
-                        Scene::WorkerDetails(
-                            WorkerDetailsView{worker}
-                        )
-                    )
+            log!(model, "Msg::GetWorker({:?})", id);
+            let req = model.get::<contract::GetWorker>(&[&id]);
+            let cached = model.cache.worker(id).map(worker_scene);
+            fetch_cached!(model, contract::GetWorker, req, cached, |worker: Option<WorkerDetails>| {
+                worker.map(worker_scene)
+            }, Msg::Null);
+        }
+        Msg::GetMyTasks => {
+            log!(model, "Msg::GetMyTasks");
+            match model.user.as_ref().and_then(|user| user.worker_id) {
+                Some(id) => {
+                    let req = model.get::<contract::GetWorker>(&[&id]);
+                    let cached = model.cache.worker(id).map(my_tasks_scene);
+                    fetch_cached!(model, contract::GetWorker, req, cached, |worker: Option<WorkerDetails>| {
+                        worker.map(my_tasks_scene)
+                    }, Msg::Error("Your worker record is gone.".into()));
                 }
-                else {
-                    Msg::Null
+                None => {
+                    model.link.send_self(Msg::Error("Your login isn't linked to a worker.".into()));
                 }
-            });
+            }
         }
         Msg::GetTask(id) => {
-            log!("Msg::GetTask({:?})", id);
-            let req = model.get(&format!("get_task/{}", id));
-            fetch!(model, req, |task: Option<TaskDetails>| {
-                if let Some(task) = task {
-                    Msg::ChangeScene(
-                        Scene::TaskDetails(
-                            TaskDetailsView{task}
-                        )
-                    )
-                }
-                else {
-                    Msg::Null
-                }
-            });
+            log!(model, "Msg::GetTask({:?})", id);
+            let req = model.get::<contract::GetTask>(&[&id]);
+            let cached = model.cache.task(id).map(task_scene);
+            fetch_cached!(model, contract::GetTask, req, cached, |task: Option<TaskDetails>| {
+                task.map(task_scene)
+            }, Msg::Null);
         }
         Msg::PreViewAssignTask(id) => {
-            log!("Msg::PreViewAssignTask({})", id);
-            let req = model.get(&format!("get_task/{}", id));
-            fetch!(model, req, |task: Option<TaskDetails>| {
+            log!(model, "Msg::PreViewAssignTask({})", id);
+            let req = model.get::<contract::GetTask>(&[&id]);
+            fetch!(model, contract::GetTask, req, |task: Option<TaskDetails>| {
                 if let Some(task) = task {
                     Msg::ViewAssignTask(task)
                 }
@@ -423,22 +1056,22 @@ pub fn update(model: &mut Model, msg: Msg) -> ShouldRender {
             });
         }
         Msg::ViewAssignTask(task) => {
-            log!("Msg::ViewAssignTask({})", task.id);
+            log!(model, "Msg::ViewAssignTask({})", task.id);
             model.temp.task = Some(task);
-            let req = model.get("get_workers");
-            fetch!(model, req, move |workers: Vec<ListWorker>| {
+            let req = model.get::<contract::GetWorkers>(&[]);
+            fetch!(model, contract::GetWorkers, req, move |workers: Vec<ListWorker>| {
                 Msg::PostViewAssignTask(workers)
             });
         }
         Msg::PostViewAssignTask(workers) => {
-            log!("Msg::PostViewAssignTask()");
+            log!(model, "Msg::PostViewAssignTask()");
             match model.temp.task.take() {
                 None => { () }
                 Some(task) => {
                     model.link.send_self(
                         Msg::ChangeScene(
                             Scene::AssignTask(
-                                AssignTaskView{ task, workers }
+                                AssignTaskView{ task, workers, perms: Perms::default() }
                             )
                         )
                     );
@@ -446,56 +1079,1358 @@ pub fn update(model: &mut Model, msg: Msg) -> ShouldRender {
             }
         }
         Msg::AssignTask((task_id, worker_id)) => {
-            log!("Msg::AssignTask({},{})", task_id, worker_id);
-            let params = AssignTaskParams { task_id, worker_id };
-            let req = model.post("assign_task", &params);
-            fetch!(model, req, move |_: ()| {
-                Msg::GetTask(task_id)
-            });
+            log!(model, "Msg::AssignTask({},{})", task_id, worker_id);
+            let version = model.cache.task_version(task_id);
+            let params = AssignTaskParams { task_id, worker_id, version };
+            let change = Change::TaskAssigned { task_id, worker_id };
+            save_optimistically::<contract::AssignTask>(model, &params, change);
+            // From the picker, straight on to the task.
+            if let (Scene::AssignTask(_), Some(task)) = (&model.scene, model.cache.task(task_id)) {
+                model.link.send_self(Msg::ChangeScene(task_scene(task)));
+            }
         }
         Msg::UnassignTask(task_id) => {
-            log!("Msg::UnassignTask({})", task_id);
-            let params = UnassignTaskParams { task_id };
-            let req = model.post("unassign_task", &params);
-            fetch!(model, req, move |_: ()| {
-                Msg::GetTask(task_id)
-            });
+            log!(model, "Msg::UnassignTask({})", task_id);
+            let version = model.cache.task_version(task_id);
+            let params = UnassignTaskParams { task_id, version };
+            let change = Change::TaskUnassigned { task_id };
+            save_optimistically::<contract::UnassignTask>(model, &params, change);
         }
         Msg::AddProject(name) => {
-            log!("Msg::AddProject");
+            log!(model, "Msg::AddProject");
+            clear_input(model);
+            let project = ListProject { id: model.cache.unsaved_id(), name: name.clone(), task_count: 0 };
             let params = AddProjectParams { name };
-            let req = model.post("add_project", &params);
-            fetch!(model, req, |_: ProjectId| {
-                Msg::RefreshScene
-            });
+            save_optimistically::<contract::AddProject>(model, &params, Change::ProjectAdded { project });
         }
         Msg::AddWorker(name) => {
-            log!("Msg::AddWorker");
+            log!(model, "Msg::AddWorker");
+            clear_input(model);
+            let worker = ListWorker { id: model.cache.unsaved_id(), name: name.clone(), task_count: 0 };
             let params = AddWorkerParams { name };
-            let req = model.post("add_worker", &params);
-            fetch!(model, req, |_: WorkerId| {
-                Msg::RefreshScene
-            });
+            save_optimistically::<contract::AddWorker>(model, &params, Change::WorkerAdded { worker });
         }
         Msg::AddTask((name,project_id)) => {
-            log!("Msg::AddTask");
-            let params = AddTaskParams { name, project_id };
-            let req = model.post("add_task", &params);
-            fetch!(model, req, |_: TaskId| {
-                Msg::RefreshScene
-            });
+            log!(model, "Msg::AddTask");
+            clear_input(model);
+            let task = ListTask { id: model.cache.unsaved_id(), name: name.clone(), status: TaskStatus::Unassigned };
+            let version = model.cache.project_version(project_id);
+            let params = AddTaskParams { name, project_id, version };
+            save_optimistically::<contract::AddTask>(model, &params, Change::TaskAdded { project_id, task });
         }
-        Msg::GetUsers => {
-            log!("Msg::GetUsers");
-            let req = model.get("get_users");
-            fetch!(model, req, |users: Vec<User>| {
-                Msg::ChangeScene(
-                    Scene::Users(
-                        UsersView{users}
+        Msg::GetUsers(page) => {
+            log!(model, "Msg::GetUsers({})", page);
+            // Only admins get the button, but the URL is open to anyone.
+            if model.perms.has(Perm::ManageUsers) {
+                let req = model.get_page::<contract::GetUsersPage>(page, &list_for(model, "users"));
+                fetch!(model, contract::GetUsersPage, req, |users: Page<User>| {
+                    match past_the_end(&users) {
+                        Some(last) => Msg::GetUsers(last),
+                        None       => Msg::ViewUsers(users),
+                    }
+                });
+            }
+            else {
+                model.link.send_self(Msg::Error("Managing users needs the manage users permission.".into()));
+            }
+        }
+        Msg::ViewUsers(users) => {
+            log!(model, "Msg::ViewUsers({})", users.index());
+            model.temp.users = Some(users);
+            let req = model.get::<contract::GetRoles>(&[]);
+            fetch!(model, contract::GetRoles, req, Msg::PostViewUsers);
+        }
+        Msg::PostViewUsers(roles) => {
+            log!(model, "Msg::PostViewUsers()");
+            if let Some(users) = model.temp.users.take() {
+                model.link.send_self(
+                    Msg::ChangeScene(
+                        Scene::Users(
+                            UsersView{
+                                users,
+                                roles,
+                                list: ListState::default(),
+                                perms: Perms::default(),
+                                layout: Layout::default(),
+                            }
+                        )
                     )
-                )
-            });
+                );
+            }
         }
-    }
-    true
+        Msg::GrantRole((user_id, role_id)) => {
+            log!(model, "Msg::GrantRole({}, {})", user_id, role_id);
+            let role = match &model.scene {
+                Scene::Users(view) => view.roles.iter().find(|role| role.id == role_id).cloned(),
+                _                  => None,
+            };
+            let params = GrantRoleParams { user_id: user_id.clone(), role_id };
+            match role {
+                Some(role) => {
+                    let change = Change::RoleGranted { user_id, role };
+                    save_optimistically::<contract::GrantRole>(model, &params, change);
+                }
+                // Not from the users scene, so nothing to show it on.
+                None => {
+                    let req = model.post::<contract::GrantRole>(&params);
+                    fetch!(model, contract::GrantRole, req, |_: ()| {
+                        Msg::RefreshScene
+                    }, Msg::Failed);
+                }
+            }
+        }
+        Msg::LinkWorker((user_id, worker_id)) => {
+            log!(model, "Msg::LinkWorker({}, {:?})", user_id, worker_id);
+            clear_input(model);
+            let params = LinkWorkerParams { user_id: user_id.clone(), worker_id };
+            let req = model.post::<contract::LinkWorker>(&params);
+            fetch!(model, contract::LinkWorker, req, |_: ()| {
+                Msg::PostLinkWorker((user_id.clone(), worker_id))
+            }, Msg::Failed);
+        }
+        Msg::PostLinkWorker((user_id, worker_id)) => {
+            log!(model, "Msg::PostLinkWorker()");
+            // Linking yourself changes what "me" means right away.
+            if let Some(user) = model.user.as_mut().filter(|user| user.id == user_id) {
+                user.worker_id = worker_id;
+                model.scene.set_user(&model.perms, worker_id);
+            }
+            model.link.send_self(Msg::RefreshScene);
+        }
+        Msg::RevokeRole((user_id, role_id)) => {
+            log!(model, "Msg::RevokeRole({}, {})", user_id, role_id);
+            let params = RevokeRoleParams { user_id: user_id.clone(), role_id: role_id.clone() };
+            let change = Change::RoleRevoked { user_id, role_id };
+            save_optimistically::<contract::RevokeRole>(model, &params, change);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fakes::*;
+
+    fn project(id: ProjectId) -> ProjectDetails {
+        ProjectDetails {
+            id,
+            name: format!("Project {}", id),
+            tasks: vec![
+                ListTask { id: 1, name: "Write".into(), status: TaskStatus::Assigned },
+                ListTask { id: 2, name: "Review".into(), status: TaskStatus::Unassigned },
+            ],
+            version: 1,
+        }
+    }
+
+    fn projects(offset: usize, total: usize) -> Page<ListProject> {
+        Page {
+            items: vec![
+                ListProject { id: 1, name: "Web".into(), task_count: 2 },
+                ListProject { id: 2, name: "Mobile".into(), task_count: 0 },
+            ],
+            offset,
+            total,
+        }
+    }
+
+    fn user(perms: Vec<Perm>) -> User {
+        User {
+            id: "local|ann".into(),
+            roles: vec![Role { id: "role".into(), perms }],
+            worker_id: Some(5),
+        }
+    }
+
+    fn logged_in() -> AuthState {
+        AuthState::Yes(AuthUser { name: "Ann".into(), token: "tok".into(), expires_at: None })
+    }
+
+    fn refreshed(token: &str, expires_at: f64) -> AuthState {
+        AuthState::Yes(AuthUser { name: "Ann".into(), token: token.into(), expires_at: Some(expires_at) })
+    }
+
+    #[test]
+    fn get_project_shows_details_and_sets_hash() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+
+        let req = h.request().unwrap();
+        assert_eq!(req.method, Method::Get);
+        assert_eq!(req.action, "get_project/3");
+        assert_eq!(req.token, None);
+
+        h.respond(&Some(project(3)));
+
+        match &h.model.scene {
+            Scene::ProjectDetails(view) => {
+                assert_eq!(view.project.id, 3);
+                assert_eq!(view.rows().len(), 2);
+            }
+            _ => panic!("expected project details"),
+        }
+        assert_eq!(h.hash(), "#/project/3");
+        // The popstate from setting the hash must not refetch.
+        assert!(h.request().is_none());
+        assert!(h.log.borrow().iter().any(|line| line == "Msg::GetProject(3)"));
+    }
+
+    #[test]
+    fn missing_project_leaves_scene_alone() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&None::<ProjectDetails>);
+
+        assert!(matches!(h.model.scene, Scene::Null));
+        assert_eq!(h.hash(), "");
+    }
+
+    #[test]
+    fn deep_link_routes_once_auth_is_ready() {
+        let mut h = Harness::new();
+        h.set_hash("#/projects?sort=tasks&page=2");
+        h.send(Msg::Init);
+        assert!(h.request().is_none());
+
+        h.finish_auth(logged_in());
+
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "get_me");
+        h.respond(&Some(user(vec![Perm::View])));
+
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "get_projects_page/50/50?sort=tasks");
+        assert_eq!(req.token, Some("tok".into()));
+
+        h.respond(&projects(50, 120));
+
+        match &h.model.scene {
+            Scene::Projects(view) => {
+                assert_eq!(view.list.sort, SortKey::TaskCount);
+                assert_eq!(view.list.page, 1);
+                assert_eq!(view.rows()[0].name, "Mobile");
+            }
+            _ => panic!("expected projects"),
+        }
+        assert_eq!(h.hash(), "#/projects?sort=tasks&page=2");
+    }
+
+    #[test]
+    fn scenes_get_the_current_users_perms() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View, Perm::CreateTask]);
+        assert!(h.model.perms.has(Perm::CreateTask));
+
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+
+        match &mut h.model.scene {
+            Scene::ProjectDetails(view) => {
+                assert!(view.perms.has(Perm::CreateTask));
+                assert!(!view.perms.has(Perm::AssignTask));
+            }
+            _ => panic!("expected project details"),
+        }
+
+        // Logging out takes them away from the scene on screen too.
+        h.send(Msg::SetUser(None));
+        assert!(!h.model.scene.perms_mut().unwrap().has(Perm::CreateTask));
+    }
+
+    #[test]
+    fn guests_can_only_view() {
+        let mut h = Harness::new();
+        h.send(Msg::Init);
+        h.finish_auth(AuthState::No);
+
+        assert!(h.request().is_none());
+        assert!(h.model.user.is_none());
+        assert_eq!(h.model.perms, Perms::guest());
+
+        h.navigate("#/project/3");
+        let req = h.request().unwrap();
+        assert_eq!(req.token, None);
+        h.respond(&Some(project(3)));
+        assert!(!h.model.scene.perms_mut().unwrap().has(Perm::CreateTask));
+        assert!(!h.model.login_to_edit);
+    }
+
+    #[test]
+    fn guest_actions_ask_to_log_in() {
+        let mut h = Harness::new();
+        h.send(Msg::Init);
+        h.finish_auth(AuthState::No);
+
+        h.send(Msg::AddProject("Shed".into()));
+        assert!(h.request().is_none());
+        assert!(h.model.login_to_edit);
+
+        // Deep links to scenes that need an account too.
+        h.navigate("#/projects");
+        h.respond(&projects(0, 2));
+        assert!(!h.model.login_to_edit);
+        h.navigate("#/users");
+        assert!(h.request().is_none());
+        assert!(h.model.login_to_edit);
+        assert!(matches!(h.model.scene, Scene::Projects(_)));
+    }
+
+    fn log_in_with(h: &mut Harness, perms: Vec<Perm>) {
+        h.send(Msg::Init);
+        h.finish_auth(logged_in());
+        h.respond(&Some(user(perms)));
+    }
+
+    #[test]
+    fn users_scene_loads_users_then_roles() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::ManageUsers]);
+
+        h.navigate("#/users");
+        assert_eq!(h.request().unwrap().action, "get_users_page/0/50");
+        h.respond(&Page { items: vec![user(vec![])], offset: 0, total: 1 });
+        assert_eq!(h.request().unwrap().action, "get_roles");
+        h.respond(&vec![Role { id: "admin".into(), perms: vec![Perm::ManageUsers] }]);
+
+        match &h.model.scene {
+            Scene::Users(view) => {
+                assert_eq!(view.rows().len(), 1);
+                assert_eq!(view.roles[0].id, "admin");
+                assert!(view.perms.has(Perm::ManageUsers));
+            }
+            _ => panic!("expected users"),
+        }
+
+        h.send(Msg::GrantRole(("local|ann".into(), "admin".into())));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "grant_role");
+        assert_eq!(req.body.unwrap(), r#"{"user_id":"local|ann","role_id":"admin"}"#);
+        match &h.model.scene {
+            Scene::Users(view) => assert_eq!(view.users.items[0].roles.last().unwrap().id, "admin"),
+            _ => panic!("expected users"),
+        }
+
+        h.respond(&());
+        assert!(h.request().is_none());
+    }
+
+    #[test]
+    fn users_scene_needs_manage_users() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+
+        h.navigate("#/users");
+
+        assert!(h.request().is_none());
+        match &h.model.scene {
+            Scene::Error(view) => assert_eq!(view.path, "users"),
+            _ => panic!("expected error"),
+        }
+    }
+
+    fn worker(id: WorkerId) -> WorkerDetails {
+        WorkerDetails { id, name: "Ann".into(), tasks: project(1).tasks, version: 1 }
+    }
+
+    #[test]
+    fn me_shows_the_linked_workers_tasks() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+
+        h.navigate("#/me");
+        assert_eq!(h.request().unwrap().action, "get_worker/5");
+        h.respond(&Some(worker(5)));
+
+        match &h.model.scene {
+            Scene::MyTasks(view) => assert_eq!(view.rows().len(), 2),
+            _ => panic!("expected my tasks"),
+        }
+        assert_eq!(h.hash(), "#/me");
+    }
+
+    #[test]
+    fn me_without_a_linked_worker_is_an_error() {
+        let mut h = Harness::new();
+        h.send(Msg::Init);
+        h.finish_auth(logged_in());
+        h.respond(&Some(User { worker_id: None, ..user(vec![Perm::View]) }));
+
+        h.navigate("#/me");
+
+        assert!(h.request().is_none());
+        assert!(matches!(h.model.scene, Scene::Error(_)));
+    }
+
+    #[test]
+    fn task_details_offers_assign_to_me() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+
+        h.send(Msg::GetTask(9));
+        let task = TaskDetails {
+            id: 9,
+            name: "Deploy".into(),
+            project: ListProject { id: 3, name: "Web".into(), task_count: 1 },
+            worker: None,
+            version: 1,
+        };
+        h.respond(&Some(task));
+
+        match &h.model.scene {
+            Scene::TaskDetails(view) => assert_eq!(view.me, Some(5)),
+            _ => panic!("expected task details"),
+        }
+    }
+
+    #[test]
+    fn linking_yourself_updates_me() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::ManageUsers]);
+        h.send(Msg::GetWorker(7));
+        h.respond(&Some(worker(7)));
+
+        h.send(Msg::LinkWorker(("local|ann".into(), Some(7))));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "link_worker");
+        assert_eq!(req.body.unwrap(), r#"{"user_id":"local|ann","worker_id":7}"#);
+
+        h.respond(&());
+        assert_eq!(h.model.user.as_ref().unwrap().worker_id, Some(7));
+        assert_eq!(h.request().unwrap().action, "get_worker/7");
+    }
+
+    #[test]
+    fn a_failed_link_keeps_the_scene() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::ManageUsers]);
+        h.send(Msg::GetWorker(7));
+        h.respond(&Some(worker(7)));
+
+        h.send(Msg::LinkWorker(("local|ann".into(), Some(7))));
+        h.respond_raw(200, r#"{"Err":"no such user"}"#);
+
+        assert_eq!(h.model.notice.as_deref(), Some("That didn't save: no such user"));
+        assert!(matches!(h.model.scene, Scene::WorkerDetails(_)));
+        assert_eq!(h.model.user.as_ref().unwrap().worker_id, Some(5));
+        assert!(h.request().is_none());
+    }
+
+    #[test]
+    fn filtering_a_paged_list_asks_the_server() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        h.send(Msg::SetListFilter("mob".into()));
+
+        assert_eq!(h.hash(), "#/projects?q=mob");
+        // The page on screen is filtered at once.
+        match &h.model.scene {
+            Scene::Projects(view) => assert_eq!(view.rows().len(), 1),
+            _ => panic!("expected projects"),
+        }
+        assert_eq!(h.request().unwrap().action, "get_projects_page/0/50?q=mob");
+    }
+
+    #[test]
+    fn filtering_past_the_first_page_starts_over_from_it() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        h.navigate("#/projects?page=2");
+        h.respond(&projects(50, 120));
+
+        h.send(Msg::SortList(SortKey::TaskCount));
+
+        assert_eq!(h.hash(), "#/projects?sort=tasks");
+        assert_eq!(h.request().unwrap().action, "get_projects_page/0/50?sort=tasks");
+        h.respond(&projects(0, 120));
+        assert!(h.request().is_none());
+    }
+
+    #[test]
+    fn pages_past_the_end_show_the_last_one() {
+        let mut h = Harness::new();
+        h.navigate("#/projects?page=100");
+        assert_eq!(h.request().unwrap().action, "get_projects_page/4950/50");
+        h.respond(&Page { items: vec![], ..projects(4950, 120) });
+
+        assert_eq!(h.request().unwrap().action, "get_projects_page/100/50");
+        h.respond(&projects(100, 120));
+        assert_eq!(h.hash(), "#/projects?page=3");
+    }
+
+    #[test]
+    fn scrolling_renders_only_when_the_rows_shown_change() {
+        let mut h = Harness::new();
+        let tasks = (1..=200)
+            .map(|id| ListTask { id, name: format!("Task {:03}", id), status: TaskStatus::Unassigned })
+            .collect();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(ProjectDetails { tasks, ..project(3) }));
+
+        assert!(!update(&mut h.model, Msg::ScrollList(ROW_HEIGHT - 1)));
+        assert!(update(&mut h.model, Msg::ScrollList(40 * ROW_HEIGHT)));
+        assert!(!update(&mut h.model, Msg::ScrollList(40 * ROW_HEIGHT + 1)));
+        assert_eq!(h.model.scene.window().unwrap().range(200), 35..65);
+
+        // Past the end, as a shrinking list can leave it.
+        assert!(update(&mut h.model, Msg::ScrollList(1_000 * ROW_HEIGHT)));
+        assert_eq!(h.model.scene.window().unwrap().range(200), 200..200);
+
+        // Kept when the same project comes back fresh.
+        h.send(Msg::RefreshScene);
+        h.respond(&Some(project(3)));
+        assert_eq!(h.model.scene.window().unwrap().scroll_top, 1_000 * ROW_HEIGHT);
+    }
+
+    #[test]
+    fn changing_page_in_url_refetches() {
+        let mut h = Harness::new();
+        h.navigate("#/projects");
+        h.respond(&projects(0, 120));
+
+        h.navigate("#/projects?page=3");
+
+        assert_eq!(h.request().unwrap().action, "get_projects_page/100/50");
+    }
+
+    #[test]
+    fn failed_request_shows_error_scene_and_retry_refetches() {
+        let mut h = Harness::new();
+        h.navigate("#/task/9");
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+
+        h.respond_raw(500, "");
+
+        match &h.model.scene {
+            Scene::Error(view) => {
+                assert_eq!(view.message, "Not 200");
+                assert_eq!(view.path, "task/9");
+            }
+            _ => panic!("expected error"),
+        }
+
+        h.send(Msg::Retry);
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+    }
+
+    #[test]
+    fn api_error_is_shown() {
+        let mut h = Harness::new();
+        h.send(Msg::GetTask(9));
+        h.respond_raw(200, r#"{"Err":"no such task"}"#);
+
+        match &h.model.scene {
+            Scene::Error(view) => assert_eq!(view.message, "no such task"),
+            _ => panic!("expected error"),
+        }
+    }
+
+    #[test]
+    fn add_task_posts_and_shows_the_task() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+
+        h.send(Msg::SetInput("Deploy".into()));
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+
+        let req = h.request().unwrap();
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.action, "add_task");
+        assert_eq!(req.body.unwrap(), r#"{"name":"Deploy","project_id":3,"version":1}"#);
+        assert_eq!(h.model.scene.input_mut().unwrap(), "");
+
+        h.respond(&17);
+        assert!(h.request().is_none());
+        match &h.model.scene {
+            Scene::ProjectDetails(view) => assert_eq!(view.project.tasks[2].id, 17),
+            _ => panic!("expected project details"),
+        }
+    }
+
+    #[test]
+    fn unauthorized_refreshes_and_retries_once() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+
+        h.send(Msg::GetProject(3));
+        h.respond_raw(401, "");
+        assert!(h.refreshing());
+        assert!(h.request().is_none());
+
+        h.finish_refresh(refreshed("tok2", 3_600_000.0));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "get_project/3");
+        assert_eq!(req.token, Some("tok2".into()));
+
+        // A second 401 is an error, not another refresh.
+        h.respond_raw(401, "");
+        assert!(!h.refreshing());
+        assert!(matches!(h.model.scene, Scene::Error(_)));
+    }
+
+    #[test]
+    fn a_change_waiting_on_a_refresh_outlasts_moving_on() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.send(Msg::AssignTask((9, 5)));
+        h.respond_raw(401, "");
+        h.send(Msg::GetProjects(0));
+        h.finish_refresh(refreshed("tok2", 3_600_000.0));
+
+        assert_eq!(h.request().unwrap().action, "get_projects_page/0/50");
+        h.respond(&projects(0, 2));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "assign_task");
+        assert_eq!(req.token, Some("tok2".into()));
+        h.respond(&());
+        assert!(h.request().is_none());
+        assert_eq!(h.model.notice, None);
+    }
+
+    #[test]
+    fn failed_refresh_logs_out() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+
+        h.send(Msg::GetProject(3));
+        h.respond_raw(401, "");
+        h.finish_refresh(AuthState::No);
+
+        assert!(matches!(h.model.auth_state, AuthState::No));
+        assert!(h.model.session_expired);
+        assert!(h.model.user.is_none());
+        assert!(h.request().is_none());
+    }
+
+    #[test]
+    fn token_is_refreshed_before_it_expires() {
+        let mut h = Harness::new();
+        h.send(Msg::Init);
+        h.finish_auth(refreshed("tok", h.now() + 300_000.0));
+        h.respond(&Some(user(vec![Perm::View])));
+
+        h.advance(200_000.0);
+        assert!(!h.refreshing());
+        h.advance(40_000.0);
+        assert!(h.refreshing());
+
+        h.finish_refresh(refreshed("tok2", h.now() + 300_000.0));
+        assert_eq!(h.model.auth_state.token(), Some("tok2".into()));
+        assert!(!h.refreshing());
+        // And the new token gets its own refresh.
+        h.advance(240_000.0);
+        assert!(h.refreshing());
+    }
+
+    #[test]
+    fn local_provider_logs_in_as_an_account() {
+        use crate::localauth::*;
+
+        let config = LocalConfig { accounts: vec!["local|viewer".into()] };
+        let storage = FakeStorage::default();
+        let mut h = Harness::with_auth(Box::new(LocalProvider::new(config, Box::new(storage))));
+        h.send(Msg::Init);
+        assert!(matches!(h.model.auth_state, AuthState::No));
+
+        h.send(Msg::LoginAs("local|viewer".into()));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "get_me");
+        assert_eq!(req.token, Some("local|viewer".into()));
+        h.respond(&Some(user(vec![Perm::View])));
+        assert!(h.model.perms.has(Perm::View));
+
+        h.send(Msg::Logout);
+        assert!(matches!(h.model.auth_state, AuthState::No));
+        assert!(h.model.user.is_none());
+    }
+
+    #[test]
+    fn sort_order_is_remembered_per_kind_of_list() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        h.send(Msg::SortList(SortKey::Status));
+        h.send(Msg::SortList(SortKey::Status));
+
+        h.send(Msg::GetProject(4));
+        h.respond(&Some(project(4)));
+        let list = h.model.scene.list().unwrap();
+        assert_eq!((list.sort, list.desc), (SortKey::Status, true));
+        assert_eq!(h.hash(), "#/project/4?sort=status&desc");
+
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+        assert_eq!(h.model.scene.list().unwrap().sort, SortKey::Name);
+    }
+
+    #[test]
+    fn prefs_survive_a_reload() {
+        let mut h = Harness::new();
+        h.send(Msg::Init);
+        h.finish_auth(AuthState::No);
+        h.navigate("#/project/3");
+        h.respond(&Some(project(3)));
+        h.send(Msg::ToggleColumn(Column::Status));
+        h.send(Msg::ToggleSection(Section::Tasks));
+        assert!(!h.model.scene.layout_mut().unwrap().shows(Column::Status));
+
+        // Opening the app with no hash goes back to the last scene.
+        let mut reloaded = h.reload();
+        reloaded.send(Msg::Init);
+        reloaded.finish_auth(AuthState::No);
+        assert_eq!(reloaded.hash(), "#/project/3");
+        reloaded.respond(&Some(project(3)));
+
+        let layout = reloaded.model.scene.layout_mut().unwrap();
+        assert!(!layout.shows(Column::Status));
+        assert!(!layout.is_open(Section::Tasks));
+    }
+
+    #[test]
+    fn the_last_scene_only_opens_a_visit() {
+        let mut h = Harness::new();
+        let prefs = Prefs { landing: Some("project/3".into()), ..Prefs::default() };
+        prefs.save(&mut h.storage());
+        h.send(Msg::Init);
+        h.finish_auth(AuthState::No);
+        assert_eq!(h.hash(), "#/project/3");
+        h.respond(&Some(project(3)));
+
+        h.navigate("#/");
+        assert!(matches!(h.model.scene, Scene::Null));
+        assert_eq!(h.hash(), "#/");
+        assert!(h.request().is_none());
+    }
+
+    #[test]
+    fn prefs_are_saved_only_when_they_change() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        let writes = h.storage().writes();
+
+        h.send(Msg::RefreshScene);
+        h.respond(&Some(project(3)));
+        assert_eq!(h.storage().writes(), writes);
+
+        h.send(Msg::GetProject(4));
+        h.respond(&Some(project(4)));
+        assert_eq!(h.storage().writes(), writes + 1);
+    }
+
+    #[test]
+    fn drafts_come_back_per_scene() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        h.send(Msg::SetInput("Deploy".into()));
+
+        h.send(Msg::GetProject(4));
+        h.respond(&Some(project(4)));
+        assert_eq!(h.model.scene.input_mut().unwrap(), "");
+
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        assert_eq!(h.model.scene.input_mut().unwrap(), "Deploy");
+
+        // And after a reload.
+        let mut reloaded = h.reload();
+        reloaded.send(Msg::GetProject(3));
+        reloaded.respond(&Some(project(3)));
+        assert_eq!(reloaded.model.scene.input_mut().unwrap(), "Deploy");
+    }
+
+    #[test]
+    fn submitting_drops_the_draft() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        h.send(Msg::SetInput("Deploy".into()));
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+        h.respond(&17);
+
+        assert_eq!(h.model.scene.input_mut().unwrap(), "");
+        let mut reloaded = h.reload();
+        reloaded.send(Msg::GetProject(3));
+        reloaded.respond(&Some(project(3)));
+        assert_eq!(reloaded.model.scene.input_mut().unwrap(), "");
+    }
+
+    #[test]
+    fn revisits_show_the_cached_copy_then_revalidate() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        h.send(Msg::GetProject(3));
+        assert!(matches!(&h.model.scene, Scene::ProjectDetails(view) if view.project.id == 3));
+        assert_eq!(h.hash(), "#/project/3");
+        assert_eq!(h.request().unwrap().action, "get_project/3");
+
+        h.respond(&Some(ProjectDetails { name: "Renamed".into(), ..project(3) }));
+        match &h.model.scene {
+            Scene::ProjectDetails(view) => assert_eq!(view.project.name, "Renamed"),
+            _ => panic!("expected project details"),
+        }
+    }
+
+    #[test]
+    fn failed_revalidation_keeps_the_cached_copy() {
+        let mut h = Harness::new();
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        h.send(Msg::GetTask(9));
+        h.respond_raw(500, "");
+
+        assert!(matches!(&h.model.scene, Scene::TaskDetails(view) if view.task.id == 9));
+    }
+
+    fn task(id: TaskId) -> TaskDetails {
+        TaskDetails {
+            id,
+            name: "Deploy".into(),
+            project: ListProject { id: 3, name: "Web".into(), task_count: 2 },
+            worker: None,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn assigning_shows_at_once() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        h.send(Msg::GetWorker(5));
+        h.respond(&Some(worker(5)));
+        h.send(Msg::GetTask(2));
+        h.respond(&Some(TaskDetails { id: 2, name: "Review".into(), ..task(2) }));
+
+        h.send(Msg::AssignTask((2, 5)));
+
+        assert_eq!(h.request().unwrap().action, "assign_task");
+        match &h.model.scene {
+            Scene::TaskDetails(view) => assert_eq!(view.task.worker.as_ref().unwrap().id, 5),
+            _ => panic!("expected task details"),
+        }
+        assert_eq!(h.model.cache.project(3).unwrap().tasks[1].status, TaskStatus::Assigned);
+        assert!(h.model.cache.worker(5).unwrap().tasks.iter().any(|task| task.id == 2));
+
+        h.respond(&());
+        assert!(h.request().is_none());
+    }
+
+    #[test]
+    fn rejected_changes_are_rolled_back_with_a_notice() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.send(Msg::AssignTask((9, 5)));
+        h.respond_raw(200, r#"{"Err":"no such worker"}"#);
+
+        assert_eq!(h.model.notice.as_deref(), Some("That didn't save: no such worker"));
+        assert!(h.model.cache.task(9).unwrap().worker.is_none());
+        match &h.model.scene {
+            Scene::TaskDetails(view) => assert!(view.task.worker.is_none()),
+            _ => panic!("expected task details"),
+        }
+        // Nothing else was undone, so there's nothing to fetch.
+        assert!(h.request().is_none());
+
+        h.send(Msg::DismissNotice);
+        assert_eq!(h.model.notice, None);
+    }
+
+    #[test]
+    fn only_the_rejected_change_is_rolled_back() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask, Perm::CreateTask]);
+        h.send(Msg::GetWorker(5));
+        h.respond(&Some(WorkerDetails { tasks: vec![], ..worker(5) }));
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+
+        h.send(Msg::AssignTask((2, 5)));
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+        let added = unsaved_task(&h);
+        h.respond_raw(200, r#"{"Err":"no such worker"}"#);
+
+        let tasks = h.model.cache.project(3).unwrap().tasks;
+        assert_eq!(tasks[1].status, TaskStatus::Unassigned);
+        assert_eq!(tasks[2].id, added);
+        assert!(h.model.cache.worker(5).unwrap().tasks.is_empty());
+        match &h.model.scene {
+            Scene::ProjectDetails(view) => assert_eq!(view.project.tasks, tasks),
+            _ => panic!("expected project details"),
+        }
+        assert_eq!(h.model.cache.project_version(3), 2);
+
+        assert_eq!(h.request().unwrap().action, "add_task");
+        h.respond(&7);
+        assert_eq!(h.model.cache.project(3).unwrap().tasks[2].id, 7);
+    }
+
+    #[test]
+    fn added_projects_are_listed_before_the_server_answers() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        h.send(Msg::AddProject("Desktop".into()));
+        match &h.model.scene {
+            Scene::Projects(view) => {
+                assert_eq!(view.projects.total, 3);
+                assert!(view.projects.items.iter().any(|project| project.name == "Desktop" && project.id < 0));
+            }
+            _ => panic!("expected projects"),
+        }
+
+        h.respond(&3);
+        match &h.model.scene {
+            Scene::Projects(view) => assert_eq!(view.projects.items[2].id, 3),
+            _ => panic!("expected projects"),
+        }
+    }
+
+    #[test]
+    fn moving_on_does_not_cancel_a_save() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+
+        h.send(Msg::GetProjects(0));
+        assert_eq!(h.request().unwrap().action, "add_task");
+        h.respond(&17);
+        assert_eq!(h.request().unwrap().action, "get_projects_page/0/50");
+        h.respond(&projects(0, 2));
+
+        assert!(matches!(h.model.scene, Scene::Projects(_)));
+        assert_eq!(h.model.cache.project(3).unwrap().tasks[2].id, 17);
+    }
+
+    #[test]
+    fn added_tasks_show_up_before_the_server_answers() {
+        let mut h = Harness::new();
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+
+        match &h.model.scene {
+            Scene::ProjectDetails(view) => assert_eq!(view.project.tasks.last().unwrap().name, "Deploy"),
+            _ => panic!("expected project details"),
+        }
+    }
+
+    fn unsaved_task(h: &Harness) -> TaskId {
+        match &h.model.scene {
+            Scene::ProjectDetails(view) => view.project.tasks.last().unwrap().id,
+            _ => panic!("expected project details"),
+        }
+    }
+
+    #[test]
+    fn offline_changes_wait_and_go_in_order() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+
+        h.set_online(false);
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+        let unsaved = unsaved_task(&h);
+        h.send(Msg::AssignTask((unsaved, 5)));
+        assert!(h.request().is_none());
+        assert_eq!(h.model.outbox.len(), 2);
+
+        h.set_online(true);
+        assert_eq!(h.request().unwrap().action, "add_task");
+        h.respond(&17);
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "assign_task");
+        assert_eq!(req.body.unwrap(), r#"{"task_id":17,"version":0,"worker_id":5}"#);
+        h.respond(&());
+
+        assert!(h.model.outbox.is_empty());
+        assert_eq!(unsaved_task(&h), 17);
+        assert_eq!(h.model.notice, None);
+    }
+
+    #[test]
+    fn a_replay_waiting_on_a_refresh_outlasts_moving_on() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.set_online(false);
+        h.send(Msg::AssignTask((9, 5)));
+        h.set_online(true);
+        assert_eq!(h.request().unwrap().action, "assign_task");
+        h.respond_raw(401, "");
+        h.send(Msg::GetProjects(0));
+        h.finish_refresh(refreshed("tok2", 3_600_000.0));
+
+        h.respond(&projects(0, 2));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "assign_task");
+        assert_eq!(req.token, Some("tok2".into()));
+        h.respond(&());
+        assert!(h.model.outbox.is_empty());
+        assert!(!h.model.outbox.sending);
+    }
+
+    #[test]
+    fn tasks_added_and_assigned_offline_get_their_real_id_everywhere() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask, Perm::CreateTask]);
+        h.send(Msg::GetWorker(5));
+        h.respond(&Some(WorkerDetails { tasks: vec![], ..worker(5) }));
+        h.send(Msg::GetProject(3));
+        h.respond(&Some(project(3)));
+
+        h.set_online(false);
+        h.send(Msg::AddTask(("Deploy".into(), 3)));
+        let unsaved = unsaved_task(&h);
+        h.send(Msg::AssignTask((unsaved, 5)));
+        h.set_online(true);
+        h.respond(&17);
+        h.respond(&());
+
+        let ids: Vec<TaskId> = h.model.cache.worker(5).unwrap().tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, [17]);
+        assert_eq!(h.model.cache.task_version(17), 1);
+        h.send(Msg::UnassignTask(17));
+        assert_eq!(h.request().unwrap().body.unwrap(), r#"{"task_id":17,"version":1}"#);
+    }
+
+    #[test]
+    fn timeouts_from_the_server_are_sent_again_later() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.send(Msg::UnassignTask(9));
+        h.respond_raw(408, "");
+        assert!(h.model.online);
+        assert_eq!(h.model.outbox.len(), 1);
+        // Straight away once, then after a wait.
+        assert_eq!(h.request().unwrap().action, "unassign_task");
+        h.respond_raw(408, "");
+        assert!(h.request().is_none());
+        h.advance(RETRY_MS as f64);
+        assert_eq!(h.request().unwrap().action, "unassign_task");
+        h.respond(&());
+
+        assert!(h.model.outbox.is_empty());
+        assert_eq!(h.model.notice, None);
+        assert!(h.model.cache.task(9).unwrap().worker.is_none());
+    }
+
+    #[test]
+    fn network_errors_queue_instead_of_rolling_back() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.send(Msg::UnassignTask(9));
+        h.respond_raw(0, "");
+        assert!(!h.model.online);
+        assert_eq!(h.model.outbox.len(), 1);
+        assert_eq!(h.model.notice, None);
+
+        // Any answer from the server means it's back.
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+        assert!(h.model.online);
+        assert_eq!(h.request().unwrap().action, "unassign_task");
+    }
+
+    #[test]
+    fn conflicts_on_replay_are_reported() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.set_online(false);
+        h.send(Msg::AssignTask((9, 5)));
+        h.send(Msg::UnassignTask(2));
+        h.set_online(true);
+        h.respond_raw(200, r#"{"Err":"task 9 was deleted"}"#);
+        assert_eq!(h.request().unwrap().action, "unassign_task");
+        h.respond(&());
+
+        assert_eq!(h.model.notice.as_deref(),
+            Some("Some changes made offline didn't save: assigning task 9 to worker 5 (task 9 was deleted)"));
+        assert!(h.model.cache.task(9).is_none());
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+    }
+
+    #[test]
+    fn waiting_changes_survive_a_reload() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        h.set_online(false);
+        h.send(Msg::AddProject("Desktop".into()));
+
+        let mut reloaded = h.reload();
+        log_in_with(&mut reloaded, vec![Perm::View]);
+        assert_eq!(reloaded.model.outbox.len(), 1);
+        assert_eq!(reloaded.request().unwrap().action, "add_project");
+        reloaded.respond(&4);
+        assert!(reloaded.model.outbox.is_empty());
+        assert!(reloaded.model.cache.unsaved_id() < -1);
+    }
+
+    #[test]
+    fn writes_carry_the_version_they_were_made_against() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.send(Msg::AssignTask((9, 5)));
+        assert!(h.request().unwrap().body.unwrap().contains(r#""version":1"#));
+        h.respond(&());
+
+        // The server bumped it; so did the cache.
+        h.send(Msg::UnassignTask(9));
+        assert!(h.request().unwrap().body.unwrap().contains(r#""version":2"#));
+    }
+
+    #[test]
+    fn stale_writes_offer_theirs_or_mine() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.send(Msg::AssignTask((9, 5)));
+        h.respond_raw(409, r#"{"Err":"changed by someone else"}"#);
+        assert!(h.model.cache.task(9).unwrap().worker.is_none());
+
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+        let theirs = TaskDetails {
+            worker: Some(ListWorker { id: 7, name: "Bo".into(), task_count: 1 }),
+            version: 2,
+            ..task(9)
+        };
+        h.respond(&Some(theirs));
+        match &h.model.scene {
+            Scene::Conflict(view) => assert_eq!(view.change, Change::TaskAssigned { task_id: 9, worker_id: 5 }),
+            _ => panic!("expected a conflict"),
+        }
+        assert_eq!(h.hash(), "#/task/9");
+
+        h.send(Msg::Overwrite(Change::TaskAssigned { task_id: 9, worker_id: 5 }));
+        let req = h.request().unwrap();
+        assert_eq!(req.action, "assign_task");
+        assert!(req.body.unwrap().contains(r#""version":2"#));
+        match &h.model.scene {
+            Scene::TaskDetails(view) => assert_eq!(view.task.id, 9),
+            _ => panic!("expected task details"),
+        }
+    }
+
+    #[test]
+    fn stale_changes_made_offline_are_a_conflict_too() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::AssignTask]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.set_online(false);
+        h.send(Msg::AssignTask((9, 5)));
+        h.set_online(true);
+        h.respond_raw(409, r#"{"Err":"changed by someone else"}"#);
+
+        assert!(h.model.outbox.is_empty());
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+        h.respond(&Some(TaskDetails { version: 2, ..task(9) }));
+        match &h.model.scene {
+            Scene::Conflict(_) => (),
+            _ => panic!("expected a conflict"),
+        }
+        assert_eq!(h.model.cache.task_version(9), 2);
+    }
+
+    #[test]
+    fn events_update_the_scene_live() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        assert_eq!(h.connections(), ["ws://localhost:8001/api/events"]);
+        h.set_socket_open(true);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        let worker = ListWorker { id: 7, name: "Bo".into(), task_count: 1 };
+        h.push_event(&Event::Updated(Entity::Task(TaskDetails { worker: Some(worker), version: 2, ..task(9) })));
+        match &h.model.scene {
+            Scene::TaskDetails(view) => assert_eq!(view.task.worker.as_ref().unwrap().name, "Bo"),
+            _ => panic!("expected task details"),
+        }
+        assert_eq!(h.model.cache.task_version(9), 2);
+        assert!(h.request().is_none());
+
+        h.push_event(&Event::Deleted(EntityRef::Task(9)));
+        assert_eq!(h.model.notice.as_deref(), Some("Someone else deleted this."));
+    }
+
+    #[test]
+    fn dropped_connections_come_back_with_backoff() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::View]);
+        h.set_socket_open(true);
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        // An error, then the close that follows it.
+        h.set_socket_open(false);
+        h.set_socket_open(false);
+        h.advance(999.0);
+        assert_eq!(h.connections().len(), 1);
+        h.advance(1.0);
+        assert_eq!(h.connections().len(), 2);
+
+        h.set_socket_open(false);
+        h.advance(1_999.0);
+        assert_eq!(h.connections().len(), 2);
+        h.advance(1.0);
+        assert_eq!(h.connections().len(), 3);
+
+        // Back, with whatever was missed.
+        h.set_socket_open(true);
+        assert_eq!(h.request().unwrap().action, "get_projects_page/0/50");
+        assert_eq!(h.model.live.retries, 0);
+    }
+
+    #[test]
+    fn echoes_of_the_users_own_adds_are_not_listed_twice() {
+        let mut h = Harness::new();
+        log_in_with(&mut h, vec![Perm::CreateProject]);
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        h.send(Msg::AddProject("Desktop".into()));
+        h.push_event(&Event::Created(Entity::Project(ProjectDetails {
+            id: 3,
+            name: "Desktop".into(),
+            tasks: vec![],
+            version: 0,
+        })));
+        h.respond(&3);
+
+        match &h.model.scene {
+            Scene::Projects(view) => {
+                let ids: Vec<ProjectId> = view.projects.items.iter().map(|project| project.id).collect();
+                assert_eq!(ids, [1, 2, 3]);
+                assert_eq!(view.projects.total, 3);
+            }
+            _ => panic!("expected projects"),
+        }
+    }
+
+    fn poll_every_30s(h: &mut Harness, perms: Vec<Perm>) {
+        h.model.config.live = LiveConfig::Poll { every_secs: 30 };
+        log_in_with(h, perms);
+    }
+
+    fn scene_changes(h: &Harness) -> usize {
+        h.log.borrow().iter().filter(|line| *line == "Msg::ChangeScene").count()
+    }
+
+    #[test]
+    fn polls_show_changes_to_the_scene() {
+        let mut h = Harness::new();
+        poll_every_30s(&mut h, vec![Perm::View]);
+        assert!(h.connections().is_empty());
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+        let shown = scene_changes(&h);
+
+        h.tick();
+        assert_eq!(h.request().unwrap().action, "get_projects_page/0/50");
+        h.respond(&projects(0, 2));
+        assert_eq!(scene_changes(&h), shown);
+
+        let mut more = projects(0, 3);
+        more.items.push(ListProject { id: 3, name: "Desktop".into(), task_count: 0 });
+        h.tick();
+        h.respond(&more);
+        assert_eq!(scene_changes(&h), shown + 1);
+        match &h.model.scene {
+            Scene::Projects(view) => assert_eq!(view.projects.items.len(), 3),
+            _ => panic!("expected projects"),
+        }
+    }
+
+    #[test]
+    fn hidden_tabs_are_not_polled() {
+        let mut h = Harness::new();
+        poll_every_30s(&mut h, vec![Perm::View]);
+        h.send(Msg::GetTask(9));
+        h.respond(&Some(task(9)));
+
+        h.set_visible(false);
+        h.tick();
+        assert!(h.request().is_none());
+
+        h.set_visible(true);
+        h.tick();
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+    }
+
+    #[test]
+    fn polls_give_way_to_the_user() {
+        let mut h = Harness::new();
+        poll_every_30s(&mut h, vec![Perm::View]);
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        // Moving on doesn't cancel the poll, and its answer is left out.
+        h.tick();
+        h.send(Msg::GetTask(9));
+        h.respond(&projects(0, 3));
+        assert_eq!(h.request().unwrap().action, "get_task/9");
+        h.respond(&Some(task(9)));
+        match &h.model.scene {
+            Scene::TaskDetails(view) => assert_eq!(view.task.id, 9),
+            _ => panic!("expected task details"),
+        }
+    }
+
+    #[test]
+    fn polls_that_cross_a_change_are_dropped() {
+        let mut h = Harness::new();
+        poll_every_30s(&mut h, vec![Perm::CreateProject]);
+        h.send(Msg::GetProjects(0));
+        h.respond(&projects(0, 2));
+
+        h.tick();
+        h.send(Msg::AddProject("Desktop".into()));
+        h.respond(&projects(0, 2));
+        match &h.model.scene {
+            Scene::Projects(view) => assert_eq!(view.projects.items.len(), 3),
+            _ => panic!("expected projects"),
+        }
+    }
 }
@@ -0,0 +1,231 @@
+// Changes made while offline, kept in storage until they can be sent.
+// They go out one at a time, in the order they were made, when the
+// connection comes back or the app next starts (see Msg::Replay). While
+// any are waiting, new changes queue up behind them to keep that order.
+//
+// Anything added offline goes by an unsaved id (see Cache::unsaved_id),
+// and later changes may name it, e.g. assigning a task added offline.
+// Once the server gives it a real id, `created` renumbers what's left.
+
+use std::collections::VecDeque;
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
+use crate::cache::*;
+use crate::services::*;
+
+pub const OUTBOX_KEY: &str = "outbox";
+
+// How long a change the server timed out on waits before it's sent again.
+pub const RETRY_MS: u64 = 5_000;
+
+// A POST waiting to be sent, and what it does. The token is added when
+// it's sent, since the one it was made with may have expired by then.
+#[derive(Serialize,Deserialize,Debug,Clone,PartialEq)]
+pub struct Queued {
+    pub action: String,
+    pub body: Option<String>,
+    pub change: Change,
+}
+
+impl Queued {
+    pub fn new(req: &ApiRequest, change: Change) -> Self {
+        Self { action: req.action.clone(), body: req.body.clone(), change }
+    }
+
+    pub fn request(&self, token: Option<String>) -> ApiRequest {
+        ApiRequest {
+            method: Method::Post,
+            action: self.action.clone(),
+            body: self.body.clone(),
+            token,
+        }
+    }
+}
+
+// What the server made of a change.
+#[derive(Debug)]
+pub enum Answer {
+    // With the id of anything added.
+    Saved(Option<i32>),
+    Rejected(String),
+    // Made against an old version of what it changes (see Version).
+    Stale(String),
+    // Never got there.
+    Offline,
+    // Got there, but timed out (408): worth sending again in a while.
+    Retry,
+}
+
+impl Answer {
+    pub fn from(rsp: &ApiResponse) -> Self {
+        if rsp.is_offline() {
+            return Answer::Offline;
+        }
+        if rsp.status == 408 {
+            return Answer::Retry;
+        }
+        if rsp.status == 409 {
+            return Answer::Stale(rsp.body.clone());
+        }
+        if !rsp.is_success() {
+            return Answer::Rejected("Not 200".into());
+        }
+        match serde_json::from_str::<Result<Value,String>>(&rsp.body) {
+            Ok(Ok(value)) => Answer::Saved(value.as_i64().map(|id| id as i32)),
+            Ok(Err(s))    => Answer::Rejected(s),
+            Err(_)        => Answer::Rejected("JSON error?".into()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Outbox {
+    items: VecDeque<Queued>,
+    // Whether the first item has been sent and not answered yet.
+    pub sending: bool,
+    // What the server turned down during this replay, to tell the user
+    // once it's done.
+    pub conflicts: Vec<String>,
+}
+
+impl Outbox {
+    // Unreadable entries are dropped; they can't be sent anyway.
+    pub fn load(storage: &dyn Storage) -> Self {
+        let items = storage.restore(OUTBOX_KEY)
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self { items, ..Self::default() }
+    }
+
+    fn save(&self, storage: &mut dyn Storage) {
+        if self.items.is_empty() {
+            storage.remove(OUTBOX_KEY);
+        }
+        else {
+            let text = serde_json::to_string(&self.items).expect("Failed to save outbox");
+            storage.store(OUTBOX_KEY, text);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn front(&self) -> Option<&Queued> {
+        self.items.front()
+    }
+
+    pub fn push(&mut self, storage: &mut dyn Storage, queued: Queued) {
+        self.items.push_back(queued);
+        self.save(storage);
+    }
+
+    pub fn pop(&mut self, storage: &mut dyn Storage) -> Option<Queued> {
+        let queued = self.items.pop_front();
+        self.save(storage);
+        queued
+    }
+
+    // The lowest unsaved id waiting here, or 0 if none.
+    pub fn lowest_unsaved(&self) -> i32 {
+        self.items.iter()
+            .filter_map(|queued| queued.change.unsaved_id())
+            .min()
+            .unwrap_or(0)
+    }
+
+    // `unsaved` was saved as `id`: anything still waiting that names it
+    // gets the real one.
+    pub fn created(&mut self, storage: &mut dyn Storage, unsaved: i32, id: i32) {
+        for queued in self.items.iter_mut() {
+            *queued = renumber(queued, unsaved, id);
+        }
+        self.save(storage);
+    }
+}
+
+fn renumber(queued: &Queued, unsaved: i32, id: i32) -> Queued {
+    let mut body = queued.body.as_ref().and_then(|body| serde_json::from_str(body).ok());
+    let mut change = serde_json::to_value(&queued.change).expect("Failed to renumber change");
+    if let Some(body) = &mut body {
+        replace_id(body, unsaved, id);
+    }
+    replace_id(&mut change, unsaved, id);
+
+    Queued {
+        action: queued.action.clone(),
+        body: body.map(|body: Value| body.to_string()).or_else(|| queued.body.clone()),
+        change: serde_json::from_value(change).unwrap_or_else(|_| queued.change.clone()),
+    }
+}
+
+// Unsaved ids are negative and nothing else is, so any number equal to
+// one is that id.
+fn replace_id(value: &mut Value, unsaved: i32, id: i32) {
+    match value {
+        Value::Number(n) if n.as_i64() == Some(unsaved.into()) => *value = id.into(),
+        Value::Array(items)  => items.iter_mut().for_each(|item| replace_id(item, unsaved, id)),
+        Value::Object(items) => items.values_mut().for_each(|item| replace_id(item, unsaved, id)),
+        _                    => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::*;
+    use crate::fakes::*;
+
+    fn add_task(id: TaskId) -> Queued {
+        Queued {
+            action: "add_task".into(),
+            body: Some(r#"{"name":"Deploy","project_id":3}"#.into()),
+            change: Change::TaskAdded {
+                project_id: 3,
+                task: ListTask { id, name: "Deploy".into(), status: TaskStatus::Unassigned },
+            },
+        }
+    }
+
+    fn assign(task_id: TaskId) -> Queued {
+        Queued {
+            action: "assign_task".into(),
+            body: Some(format!(r#"{{"task_id":{},"worker_id":5}}"#, task_id)),
+            change: Change::TaskAssigned { task_id, worker_id: 5 },
+        }
+    }
+
+    #[test]
+    fn survives_a_reload_in_order() {
+        let mut storage = FakeStorage::default();
+        let mut outbox = Outbox::default();
+        outbox.push(&mut storage, add_task(-1));
+        outbox.push(&mut storage, assign(-1));
+
+        let mut loaded = Outbox::load(&storage);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.lowest_unsaved(), -1);
+        assert_eq!(loaded.pop(&mut storage), Some(add_task(-1)));
+        assert_eq!(Outbox::load(&storage).front(), Some(&assign(-1)));
+
+        loaded.pop(&mut storage);
+        assert_eq!(storage.restore(OUTBOX_KEY), None);
+    }
+
+    #[test]
+    fn saved_ids_replace_unsaved_ones() {
+        let mut storage = FakeStorage::default();
+        let mut outbox = Outbox::default();
+        outbox.push(&mut storage, assign(-1));
+        outbox.push(&mut storage, assign(-2));
+
+        outbox.created(&mut storage, -1, 17);
+
+        assert_eq!(outbox.pop(&mut storage), Some(assign(17)));
+        assert_eq!(outbox.pop(&mut storage), Some(assign(-2)));
+    }
+}
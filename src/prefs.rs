@@ -0,0 +1,200 @@
+// UI preferences, kept in local storage between visits: where to land,
+// how each list is sorted, and which columns and sections are hidden.
+//
+// The stored JSON is `{ "version": N, "prefs": {...} }`. Adding a field
+// with a default doesn't need a new version; renaming or reshaping one
+// does, along with a step in MIGRATIONS that rewrites the old shape.
+// Anything that can't be read falls back to the defaults.
+
+use std::collections::{ BTreeMap, BTreeSet };
+use serde::{ Serialize, Deserialize };
+use serde_json::{ json, Value };
+use crate::liststate::*;
+use crate::services::*;
+
+pub const PREFS_KEY: &str = "prefs";
+
+// MIGRATIONS[i] turns version i + 1 into version i + 2.
+type Migration = fn(Value) -> Value;
+const MIGRATIONS: &[Migration] = &[];
+const PREFS_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    TaskCount,
+    Status,
+}
+
+impl Column {
+    pub fn label(self) -> &'static str {
+        match self {
+            Column::TaskCount => "Tasks",
+            Column::Status    => "Status",
+        }
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    Tasks,
+    Roles,
+}
+
+// The prefs that change how a scene renders. Stamped onto scenes the way
+// perms are, since views can't see the model.
+#[derive(Serialize,Deserialize,Debug,Clone,Default,PartialEq)]
+#[serde(default)]
+pub struct Layout {
+    pub hidden: BTreeSet<Column>,
+    pub collapsed: BTreeSet<Section>,
+}
+
+impl Layout {
+    pub fn shows(&self, column: Column) -> bool {
+        !self.hidden.contains(&column)
+    }
+
+    pub fn is_open(&self, section: Section) -> bool {
+        !self.collapsed.contains(&section)
+    }
+
+    pub fn toggle_column(&mut self, column: Column) {
+        toggle(&mut self.hidden, column);
+    }
+
+    pub fn toggle_section(&mut self, section: Section) {
+        toggle(&mut self.collapsed, section);
+    }
+}
+
+fn toggle<T: Ord>(set: &mut BTreeSet<T>, item: T) {
+    if !set.remove(&item) {
+        set.insert(item);
+    }
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Copy,PartialEq)]
+pub struct SavedSort {
+    pub sort: SortKey,
+    pub desc: bool,
+}
+
+#[derive(Serialize,Deserialize,Debug,Clone,Default,PartialEq)]
+#[serde(default)]
+pub struct Prefs {
+    // Where to go when the app opens with no hash: the hash path of the
+    // last scene shown.
+    pub landing: Option<String>,
+    // Keyed by the first part of the scene's hash path, e.g. "project".
+    pub sorts: BTreeMap<String, SavedSort>,
+    pub layout: Layout,
+}
+
+impl Prefs {
+    pub fn load(storage: &dyn Storage) -> Self {
+        storage.restore(PREFS_KEY)
+            .and_then(|text| Self::parse(&text, MIGRATIONS))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn Storage) {
+        let stored = json!({ "version": PREFS_VERSION, "prefs": self });
+        storage.store(PREFS_KEY, stored.to_string());
+    }
+
+    fn parse(text: &str, migrations: &[Migration]) -> Option<Self> {
+        let stored: Value = serde_json::from_str(text).ok()?;
+        let version = stored["version"].as_u64()?;
+        let current = migrations.len() as u64 + 1;
+        if version == 0 || version > current {
+            // Corrupt, or written by a newer build.
+            return None;
+        }
+
+        let prefs = migrations[version as usize - 1..]
+            .iter()
+            .fold(stored["prefs"].clone(), |prefs, migrate| migrate(prefs));
+        serde_json::from_value(prefs).ok()
+    }
+
+    pub fn sort_for(&self, path: &str) -> Option<SavedSort> {
+        self.sorts.get(list_key(path)).copied()
+    }
+
+    pub fn set_sort(&mut self, path: &str, list: &ListState) {
+        self.sorts.insert(list_key(path).into(), SavedSort { sort: list.sort, desc: list.desc });
+    }
+}
+
+// Every project shares one sort order, and so on.
+fn list_key(path: &str) -> &str {
+    path.split('/').next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fakes::*;
+
+    fn prefs() -> Prefs {
+        let mut prefs = Prefs { landing: Some("projects".into()), ..Prefs::default() };
+        prefs.layout.hidden.insert(Column::Status);
+        prefs.layout.collapsed.insert(Section::Roles);
+        prefs.set_sort("project/3", &ListState { sort: SortKey::Status, desc: true, ..ListState::default() });
+        prefs
+    }
+
+    #[test]
+    fn round_trips_through_storage() {
+        let mut storage = FakeStorage::default();
+        prefs().save(&mut storage);
+
+        let loaded = Prefs::load(&storage);
+        assert_eq!(loaded, prefs());
+        assert_eq!(loaded.sort_for("project/8"), Some(SavedSort { sort: SortKey::Status, desc: true }));
+        assert_eq!(loaded.sort_for("projects"), None);
+    }
+
+    #[test]
+    fn unreadable_prefs_are_defaults() {
+        for text in &[
+            "not json",
+            r#"{ "prefs": {} }"#,
+            r#"{ "version": 0, "prefs": {} }"#,
+            r#"{ "version": 99, "prefs": {} }"#,
+            r#"{ "version": 1, "prefs": { "sorts": { "projects": { "sort": "colour", "desc": false } } } }"#,
+        ] {
+            assert_eq!(Prefs::parse(text, MIGRATIONS), None, "{}", text);
+        }
+        assert_eq!(Prefs::load(&FakeStorage::default()), Prefs::default());
+    }
+
+    #[test]
+    fn missing_and_unknown_fields_are_fine() {
+        let text = r#"{ "version": 1, "prefs": { "landing": "workers", "theme": "dark" } }"#;
+        let prefs = Prefs::parse(text, MIGRATIONS).unwrap();
+        assert_eq!(prefs.landing, Some("workers".into()));
+        assert_eq!(prefs.layout, Layout::default());
+    }
+
+    #[test]
+    fn old_versions_are_migrated_in_order() {
+        // Say version 1 called it "home" and version 2 "start".
+        fn to_2(mut prefs: Value) -> Value {
+            prefs["start"] = prefs["home"].take();
+            prefs
+        }
+        fn to_3(mut prefs: Value) -> Value {
+            prefs["landing"] = prefs["start"].take();
+            prefs
+        }
+        let migrations: &[Migration] = &[to_2, to_3];
+
+        let v1 = r#"{ "version": 1, "prefs": { "home": "workers" } }"#;
+        let v2 = r#"{ "version": 2, "prefs": { "start": "users" } }"#;
+        assert_eq!(Prefs::parse(v1, migrations).unwrap().landing, Some("workers".into()));
+        assert_eq!(Prefs::parse(v2, migrations).unwrap().landing, Some("users".into()));
+    }
+}
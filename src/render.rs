@@ -1,8 +1,10 @@
 use yew::prelude::*;
 use crate::model::*;
 use crate::msg::*;
-use crate::views::*;
 use crate::authservice::*;
+use crate::liststate::*;
+use crate::domain::*;
+use crate::prefs::*;
 
 impl Renderable<Model> for Model {
     fn view(&self) -> Html<Self> {
@@ -24,45 +26,182 @@ fn view_auth_yes(model: &Model, user: &AuthUser) -> Html<Model> {
                 { view_nav(model) }
                 <button id="btn-logout" onclick=|_| Msg::Logout>{ "Log out" }</button>
             </div>
+            { view_outbox(model) }
+            { view_notice(model) }
             { view_scene(model) }
         </div>
     }
 }
 
+// Guests can look around; see Perms::guest.
 fn view_auth_no(model: &Model) -> Html<Model> {
+    let notice = if model.login_to_edit {
+        "Log in to edit. "
+    }
+    else if model.session_expired {
+        "Your session has expired. Log in again to continue. "
+    }
+    else {
+        ""
+    };
+
     html! {
         <div>
             <div>
-                <button id="btn-login" onclick=|_| Msg::Login>{ "Log in" }</button>
+                { notice }
+                { view_login(model) }
             </div>
             <div>
-                { "Testing: " }
+                { "Browsing as a guest: " }
                 { view_nav(model) }
             </div>
+            { view_notice(model) }
+            { view_scene(model) }
         </div>
     }
 }
 
-fn view_nav(_model: &Model) -> Html<Model> {
+fn view_outbox(model: &Model) -> Html<Model> {
+    let waiting = match model.outbox.len() {
+        0 => String::new(),
+        1 => "1 change waiting to be saved. ".into(),
+        n => format!("{} changes waiting to be saved. ", n),
+    };
+    let offline = if model.online { "" } else { "Offline." };
+    if waiting.is_empty() && model.online {
+        return html! { <span /> };
+    }
+
+    html! {
+        <div class="outbox">{ waiting }{ offline }</div>
+    }
+}
+
+fn view_notice(model: &Model) -> Html<Model> {
+    match &model.notice {
+        Some(notice) => html! {
+            <div class="notice">
+                { notice }
+                <a href="javascript:void(0)" onclick=|_| Msg::DismissNotice>{ " [dismiss]" }</a>
+            </div>
+        },
+        None => html! { <span /> },
+    }
+}
+
+// A provider with test accounts gets a button for each instead of its own
+// login page.
+fn view_login(model: &Model) -> Html<Model> {
+    let accounts = model.auth.accounts();
+    if accounts.is_empty() {
+        return html! {
+            <button id="btn-login" onclick=|_| Msg::Login>{ "Log in" }</button>
+        };
+    }
+
+    html! {
+        <span>
+            { for accounts.into_iter().map(view_login_as) }
+        </span>
+    }
+}
+
+fn view_login_as(account: String) -> Html<Model> {
+    let label = format!("Log in as {}", account);
+    html! {
+        <button onclick=|_| Msg::LoginAs(account.clone())>{ label }</button>
+    }
+}
+
+fn view_nav(model: &Model) -> Html<Model> {
     html! {
         <span>
-            <button onclick=|_| Msg::GetProjects>{ "Projects" }</button>
-            <button onclick=|_| Msg::GetWorkers>{ "Workers" }</button>
-            <button onclick=|_| Msg::GetUsers>{ "Users" }</button>
+            { view_nav_button(model, Perm::View, "Projects", Msg::GetProjects) }
+            { view_nav_button(model, Perm::View, "Workers", Msg::GetWorkers) }
+            { view_nav_button(model, Perm::ManageUsers, "Users", Msg::GetUsers) }
+            { view_my_tasks_button(model) }
         </span>
     }
 }
 
+fn view_my_tasks_button(model: &Model) -> Html<Model> {
+    match model.user.as_ref().and_then(|user| user.worker_id) {
+        Some(_) => html! {
+            <button onclick=|_| Msg::GetMyTasks>{ "My Tasks" }</button>
+        },
+        None => html! { <span /> },
+    }
+}
+
+// `get` takes the page to open on.
+fn view_nav_button(model: &Model, perm: Perm, label: &str, get: fn(usize) -> Msg) -> Html<Model> {
+    if !model.perms.has(perm) {
+        return html! { <span /> };
+    }
+
+    html! {
+        <button onclick=|_| get(0)>{ label }</button>
+    }
+}
+
 fn view_scene(model: &Model) -> Html<Model> {
-    match &model.scene {
-        Scene::Null                 => model.render_template(&NullView),
-        Scene::Projects(view)       => model.render_template(view),
-        Scene::Users(view)          => model.render_template(view),
-        Scene::Workers(view)        => model.render_template(view),
-        Scene::ProjectDetails(view) => model.render_template(view),
-        Scene::WorkerDetails(view)  => model.render_template(view),
-        Scene::TaskDetails(view)    => model.render_template(view),
-        Scene::AssignTask(view)     => model.render_template(view),
+    html! {
+        <div>
+            { view_list_controls(model) }
+            { model.scene.view() }
+        </div>
     }
 }
 
+fn view_list_controls(model: &Model) -> Html<Model> {
+    match model.scene.list() {
+        None => html! { <span /> },
+        Some(list) => html! {
+            <div>
+                <input placeholder="Filter"
+                    value=&list.filter
+                    oninput=|e| Msg::SetListFilter(e.value) />
+                { " Sort: " }
+                { for model.scene.sort_keys().iter().map(|&key| view_sort_button(list, key)) }
+                { view_column_toggles(model) }
+            </div>
+        },
+    }
+}
+
+fn view_column_toggles(model: &Model) -> Html<Model> {
+    let columns = model.scene.columns();
+    if columns.is_empty() {
+        return html! { <span /> };
+    }
+
+    html! {
+        <span>
+            { " Show: " }
+            { for columns.iter().map(|&column| view_column_toggle(&model.prefs.layout, column)) }
+        </span>
+    }
+}
+
+fn view_column_toggle(layout: &Layout, column: Column) -> Html<Model> {
+    html! {
+        <label>
+            <input type="checkbox"
+                checked=layout.shows(column)
+                onclick=|_| Msg::ToggleColumn(column) />
+            { column.label() }
+        </label>
+    }
+}
+
+fn view_sort_button(list: &ListState, key: SortKey) -> Html<Model> {
+    let label = match (list.sort == key, list.desc) {
+        (true, false) => format!("{} ▲", key.label()),
+        (true, true)  => format!("{} ▼", key.label()),
+        (false, _)    => key.label().to_string(),
+    };
+
+    html! {
+        <button onclick=|_| Msg::SortList(key)>{ label }</button>
+    }
+}
@@ -0,0 +1,152 @@
+// JSON Schema for the types that cross the wire, so the API contract can
+// be published (see contract.rs). Domain structs get this from
+// `domain_derive!`; the impls here cover the building blocks.
+
+use serde_json::{ json, Map, Value };
+
+// Named schemas, keyed by type name. Structs register themselves here and
+// are referred to with a $ref.
+pub type Definitions = Map<String, Value>;
+
+pub const REF_PREFIX: &str = "#/components/schemas/";
+
+pub trait Schema {
+    fn schema(defs: &mut Definitions) -> Value;
+
+    // May be left out or null on the wire.
+    fn optional() -> bool {
+        false
+    }
+}
+
+impl Schema for () {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "null" })
+    }
+}
+
+impl Schema for bool {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "boolean" })
+    }
+}
+
+impl Schema for i32 {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "integer", "format": "int32" })
+    }
+}
+
+impl Schema for u64 {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl Schema for usize {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl Schema for String {
+    fn schema(_: &mut Definitions) -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema(defs: &mut Definitions) -> Value {
+        json!({ "type": "array", "items": T::schema(defs) })
+    }
+}
+
+impl<T: Schema> Schema for Option<T> {
+    fn schema(defs: &mut Definitions) -> Value {
+        json!({ "oneOf": [ T::schema(defs), { "type": "null" } ] })
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
+
+// The envelope every response comes in: serde's encoding of
+// `Result<T, String>`.
+pub fn envelope(ok: Value) -> Value {
+    json!({
+        "oneOf": [
+            {
+                "type": "object",
+                "properties": { "Ok": ok },
+                "required": ["Ok"],
+                "additionalProperties": false,
+            },
+            {
+                "type": "object",
+                "properties": { "Err": { "type": "string" } },
+                "required": ["Err"],
+                "additionalProperties": false,
+            },
+        ]
+    })
+}
+
+// Registers a named schema for an enum the way serde encodes it: an
+// object with one key, the variant, holding its content. `variants` is
+// (name, schema) for each.
+pub fn define_enum(
+    defs: &mut Definitions,
+    name: &str,
+    variants: impl FnOnce(&mut Definitions) -> Vec<(&'static str, Value)>,
+    ) -> Value
+{
+    if !defs.contains_key(name) {
+        defs.insert(name.into(), Value::Null);
+
+        let one_of: Vec<Value> = variants(defs)
+            .into_iter()
+            .map(|(variant, schema)| json!({
+                "type": "object",
+                "properties": { variant: schema },
+                "required": [variant],
+                "additionalProperties": false,
+            }))
+            .collect();
+
+        defs.insert(name.into(), json!({ "oneOf": one_of }));
+    }
+
+    json!({ "$ref": format!("{}{}", REF_PREFIX, name) })
+}
+
+// Registers a named object schema once and returns a $ref to it.
+// `fields` is (name, schema, required) for each field.
+pub fn define_object(
+    defs: &mut Definitions,
+    name: &str,
+    fields: impl FnOnce(&mut Definitions) -> Vec<(&'static str, Value, bool)>,
+    ) -> Value
+{
+    if !defs.contains_key(name) {
+        // Placeholder first so recursive types terminate.
+        defs.insert(name.into(), Value::Null);
+
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for (field, schema, is_required) in fields(defs) {
+            properties.insert(field.into(), schema);
+            if is_required {
+                required.push(field);
+            }
+        }
+
+        defs.insert(name.into(), json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }));
+    }
+
+    json!({ "$ref": format!("{}{}", REF_PREFIX, name) })
+}
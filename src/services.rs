@@ -0,0 +1,403 @@
+#![allow(unused_imports)]
+
+// The side effects `update` needs, behind traits so the message flow can
+// run against in-memory fakes (see fakes.rs) as well as the browser.
+
+use yew::prelude::*;
+use yew::services::fetch::*;
+use yew::services::storage::*;
+use yew::services::timeout::*;
+use yew::services::interval::*;
+use yew::services::websocket::*;
+use yew::services::Task;
+use std::time::Duration;
+use std::collections::HashMap;
+use yew::format::{ Text, Binary };
+use yew::format::nothing::*;
+use stdweb::js;
+use stdweb::unstable::TryInto;
+use crate::msg::*;
+pub use crate::contract::Method;
+
+// A call to "{api_url}/api/{action}". The body is already JSON.
+#[derive(Debug,Clone,PartialEq)]
+pub struct ApiRequest {
+    pub method: Method,
+    pub action: String,
+    pub body: Option<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug,Clone)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl ApiResponse {
+    pub fn is_success(&self) -> bool {
+        self.status >= 200 && self.status < 300
+    }
+
+    // Never got to the server (see WebHttp::start).
+    pub fn is_offline(&self) -> bool {
+        self.status == 0
+    }
+}
+
+pub trait Http {
+    // Starting a GET cancels the GET in flight, if any. POSTs are left to
+    // finish: their changes are already on screen (see Msg::Rejected).
+    fn fetch(&mut self, req: ApiRequest, callback: Callback<ApiResponse>);
+    // A GET that runs alongside the rest: it neither cancels the GET in
+    // flight nor is cancelled by the next one.
+    fn poll(&mut self, req: ApiRequest, callback: Callback<ApiResponse>);
+    // Cancels the GET in flight.
+    fn cancel(&mut self);
+}
+
+pub trait Location {
+    // `callback` fires whenever the hash changes.
+    fn init(&mut self, callback: Callback<()>);
+    fn get_hash_path(&self) -> Vec<String>;
+    fn get_hash_query(&self) -> String;
+    fn set_hash_path(&self, path: String);
+}
+
+// An identity provider: Auth0, or a local one for development (see
+// authservice.rs and localauth.rs).
+pub trait Auth {
+    // `callback` gets the state once it's known, and again whenever a
+    // login or logout changes it without reloading the page.
+    fn init(&mut self, callback: Callback<crate::authservice::AuthState>);
+    // Goes to the provider's login page, if it has one.
+    fn login(&mut self);
+    // Logs in as one of `accounts`.
+    fn login_as(&mut self, _account: &str) {
+        self.login();
+    }
+    fn logout(&mut self);
+    // Gets a fresh token without user interaction. `callback` gets Yes with
+    // the new token, or No if the session can't be renewed.
+    fn refresh(&mut self, callback: Callback<crate::authservice::AuthState>);
+    // Accounts to offer on the login screen. Providers with a login page
+    // of their own have none.
+    fn accounts(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+pub trait Storage {
+    fn store(&mut self, key: &str, value: String);
+    fn restore(&self, key: &str) -> Option<String>;
+    fn remove(&mut self, key: &str);
+}
+
+pub trait Logger {
+    fn log(&self, msg: &str);
+}
+
+pub trait Timer {
+    // Milliseconds since the epoch.
+    fn now(&self) -> f64;
+    // Replaces whatever was scheduled before under the same name.
+    fn schedule(&mut self, name: &'static str, delay_ms: u64, callback: Callback<()>);
+}
+
+pub trait Interval {
+    // Calls `callback` every `every_ms` from now on, in place of whatever
+    // was started before.
+    fn start(&mut self, every_ms: u64, callback: Callback<()>);
+}
+
+pub trait Visibility {
+    // False while the page is in a background tab or a minimized window.
+    fn is_visible(&self) -> bool;
+}
+
+pub trait Network {
+    // `callback` gets true when the browser gets a connection back, and
+    // false when it loses it.
+    fn init(&mut self, callback: Callback<bool>);
+    fn is_online(&self) -> bool;
+}
+
+pub trait Socket {
+    // Opens a connection to `url`, in place of the one before, if any.
+    // `on_message` gets each text message. `on_open` gets true once it's
+    // open, and false when it closes or can't be opened.
+    fn connect(&mut self, url: &str, on_message: Callback<String>, on_open: Callback<bool>);
+}
+
+// One of each, for Model::with_services.
+pub struct Services {
+    pub auth: Box<dyn Auth>,
+    pub loc: Box<dyn Location>,
+    pub http: Box<dyn Http>,
+    pub storage: Box<dyn Storage>,
+    pub logger: Box<dyn Logger>,
+    pub timer: Box<dyn Timer>,
+    pub network: Box<dyn Network>,
+    pub socket: Box<dyn Socket>,
+    pub interval: Box<dyn Interval>,
+    pub visibility: Box<dyn Visibility>,
+}
+
+// Sends messages back into `update`. Mirrors the bits of ComponentLink
+// that `update` uses, but can be backed by any Callback<Msg>.
+#[derive(Clone)]
+pub struct Link {
+    sink: Callback<Msg>,
+}
+
+impl Link {
+    pub fn new(sink: Callback<Msg>) -> Self {
+        Self { sink }
+    }
+
+    pub fn send_self(&self, msg: Msg) {
+        self.sink.emit(msg);
+    }
+
+    pub fn send_back<F, IN>(&self, function: F) -> Callback<IN>
+        where F: Fn(IN) -> Msg + 'static
+    {
+        let sink = self.sink.clone();
+        Callback::from(move |x| sink.emit(function(x)))
+    }
+}
+
+pub struct WebHttp {
+    api_url: String,
+    fetcher: FetchService,
+    task: Option<FetchTask>,
+    // POSTs and polls still running; dropping one would abort it.
+    detached: Vec<FetchTask>,
+}
+
+impl WebHttp {
+    pub fn new(api_url: String) -> Self {
+        Self {
+            api_url,
+            fetcher: FetchService::new(),
+            task: None,
+            detached: Vec::new(),
+        }
+    }
+
+    fn start(&mut self, req: ApiRequest, callback: Callback<ApiResponse>) -> FetchTask {
+        let url = format!("{}/api/{}", self.api_url, req.action);
+        // Yew's fetch reports a network error as a 408 with no headers
+        // and no body. It's a 0 from here on, so that a 408 from the
+        // server isn't taken for one.
+        let callback = move |rsp: Response<Text>| {
+            let (meta, body) = rsp.into_parts();
+            let status = match (meta.status.as_u16(), meta.headers.is_empty(), &body) {
+                (408, true, Err(_)) => 0,
+                (status, _, _)      => status,
+            };
+            callback.emit(ApiResponse {
+                status,
+                body: body.unwrap_or_default(),
+            });
+        };
+
+        let mut builder = match req.method {
+            Method::Get  => Request::get(url),
+            Method::Post => Request::post(url),
+        };
+        if let Some(token) = &req.token {
+            builder.header("Authorization", format!("Bearer {}", token));
+        }
+
+        match req.body {
+            None => {
+                let request = builder
+                    .body(Nothing)
+                    .expect("Failed to build request");
+                self.fetcher.fetch(request, callback.into())
+            }
+            Some(body) => {
+                let request = builder
+                    .header("Content-Type", "application/json")
+                    .body(Ok(body))
+                    .expect("Failed to build request");
+                self.fetcher.fetch(request, callback.into())
+            }
+        }
+    }
+
+    fn detach(&mut self, task: FetchTask) {
+        self.detached.retain(|task| task.is_active());
+        self.detached.push(task);
+    }
+}
+
+impl Http for WebHttp {
+    fn fetch(&mut self, req: ApiRequest, callback: Callback<ApiResponse>) {
+        let method = req.method;
+        let task = self.start(req, callback);
+        match method {
+            Method::Get  => self.task = Some(task),
+            Method::Post => self.detach(task),
+        }
+    }
+
+    fn poll(&mut self, req: ApiRequest, callback: Callback<ApiResponse>) {
+        let task = self.start(req, callback);
+        self.detach(task);
+    }
+
+    fn cancel(&mut self) {
+        self.task = None;
+    }
+}
+
+pub struct WebStorage(StorageService);
+
+impl WebStorage {
+    pub fn new() -> Self {
+        WebStorage(StorageService::new(Area::Local))
+    }
+}
+
+impl Storage for WebStorage {
+    fn store(&mut self, key: &str, value: String) {
+        self.0.store(key, Ok(value));
+    }
+
+    fn restore(&self, key: &str) -> Option<String> {
+        let text: Text = self.0.restore(key);
+        text.ok()
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+}
+
+pub struct ConsoleLogger;
+
+impl Logger for ConsoleLogger {
+    fn log(&self, msg: &str) {
+        js!{ console.log(@{msg}); };
+    }
+}
+
+pub struct WebTimer {
+    timeout: TimeoutService,
+    tasks: HashMap<&'static str, TimeoutTask>,
+}
+
+impl WebTimer {
+    pub fn new() -> Self {
+        Self {
+            timeout: TimeoutService::new(),
+            tasks: HashMap::new(),
+        }
+    }
+}
+
+impl Timer for WebTimer {
+    fn now(&self) -> f64 {
+        js!( return Date.now(); )
+        .try_into()
+        .unwrap()
+    }
+
+    fn schedule(&mut self, name: &'static str, delay_ms: u64, callback: Callback<()>) {
+        self.tasks.insert(name, self.timeout.spawn(Duration::from_millis(delay_ms), callback));
+    }
+}
+
+pub struct WebNetwork;
+
+impl Network for WebNetwork {
+    fn init(&mut self, callback: Callback<bool>) {
+        let notify = move |online: bool| callback.emit(online);
+        js! {
+            var notify = @{notify};
+            window.addEventListener("online", function() { notify(true); });
+            window.addEventListener("offline", function() { notify(false); });
+        };
+    }
+
+    fn is_online(&self) -> bool {
+        js!( return navigator.onLine; )
+        .try_into()
+        .unwrap_or(true)
+    }
+}
+
+// A message as it arrives. Only text is expected.
+struct Frame(Option<String>);
+
+impl From<Text> for Frame {
+    fn from(text: Text) -> Self {
+        Frame(text.ok())
+    }
+}
+
+impl From<Binary> for Frame {
+    fn from(_: Binary) -> Self {
+        Frame(None)
+    }
+}
+
+pub struct WebSocket {
+    service: WebSocketService,
+    task: Option<WebSocketTask>,
+}
+
+impl WebSocket {
+    pub fn new() -> Self {
+        Self {
+            service: WebSocketService::new(),
+            task: None,
+        }
+    }
+}
+
+impl Socket for WebSocket {
+    fn connect(&mut self, url: &str, on_message: Callback<String>, on_open: Callback<bool>) {
+        let on_frame = move |frame: Frame| {
+            if let Frame(Some(text)) = frame {
+                on_message.emit(text);
+            }
+        };
+        let on_status = move |status: WebSocketStatus| {
+            on_open.emit(matches!(status, WebSocketStatus::Opened));
+        };
+        self.task = Some(self.service.connect(url, on_frame.into(), on_status.into()));
+    }
+}
+
+pub struct WebInterval {
+    interval: IntervalService,
+    task: Option<IntervalTask>,
+}
+
+impl WebInterval {
+    pub fn new() -> Self {
+        Self {
+            interval: IntervalService::new(),
+            task: None,
+        }
+    }
+}
+
+impl Interval for WebInterval {
+    fn start(&mut self, every_ms: u64, callback: Callback<()>) {
+        self.task = Some(self.interval.spawn(Duration::from_millis(every_ms), callback));
+    }
+}
+
+pub struct WebVisibility;
+
+impl Visibility for WebVisibility {
+    fn is_visible(&self) -> bool {
+        js!( return document.visibilityState !== "hidden"; )
+        .try_into()
+        .unwrap_or(true)
+    }
+}
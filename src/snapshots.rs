@@ -0,0 +1,501 @@
+// Renders every view with fixture data and compares the markup with the
+// snapshots in fixtures/snapshots. After an intended change to a view, run
+// `UPDATE_SNAPSHOTS=1 cargo test --lib` and review the diff.
+
+use std::fmt::Write;
+use yew::prelude::*;
+use yew::virtual_dom::VNode;
+use crate::domain::*;
+use crate::listwindow::*;
+use crate::liststate::*;
+use crate::model::*;
+use crate::prefs::*;
+use crate::views::*;
+use crate::cache::*;
+
+// One node per line, children indented. Attributes are sorted and
+// listeners shown by event name, so the output is stable.
+fn write_node(node: &VNode<Model>, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    match node {
+        VNode::VTag(tag) => {
+            let mut open = format!("<{}", tag.tag());
+            let classes = tag.classes.to_string();
+            if !classes.is_empty() {
+                write!(open, " class=\"{}\"", escape(&classes)).unwrap();
+            }
+            if let Some(kind) = &tag.kind {
+                write!(open, " type=\"{}\"", escape(kind)).unwrap();
+            }
+            if let Some(value) = &tag.value {
+                write!(open, " value=\"{}\"", escape(value)).unwrap();
+            }
+            let mut attributes: Vec<_> = tag.attributes.iter().collect();
+            attributes.sort();
+            for (name, value) in attributes {
+                write!(open, " {}=\"{}\"", name, escape(value)).unwrap();
+            }
+            if tag.checked {
+                open.push_str(" checked");
+            }
+            let mut listeners: Vec<_> = tag.listeners.iter().map(|l| l.kind()).collect();
+            listeners.sort();
+            for kind in listeners {
+                write!(open, " @{}", kind).unwrap();
+            }
+            open.push('>');
+
+            let inline = tag.children.iter().all(|c| matches!(c, VNode::VText(_)));
+            if inline {
+                let text: String = tag.children.iter()
+                    .map(|c| match c {
+                        VNode::VText(t) => escape(&t.text),
+                        _               => unreachable!(),
+                    })
+                    .collect();
+                writeln!(out, "{}{}{}</{}>", indent, open, text, tag.tag()).unwrap();
+            }
+            else {
+                writeln!(out, "{}{}", indent, open).unwrap();
+                for child in &tag.children {
+                    write_node(child, depth + 1, out);
+                }
+                writeln!(out, "{}</{}>", indent, tag.tag()).unwrap();
+            }
+        }
+        VNode::VText(text) => writeln!(out, "{}{}", indent, escape(&text.text)).unwrap(),
+        VNode::VList(list) => {
+            for child in &list.children {
+                write_node(child, depth, out);
+            }
+        }
+        VNode::VComp(_) => writeln!(out, "{}<component/>", indent).unwrap(),
+        VNode::VRef(_)  => writeln!(out, "{}<raw/>", indent).unwrap(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+     .replace('"', "&quot;")
+}
+
+fn render(view: &impl Renderable<Model>) -> String {
+    let mut out = String::new();
+    write_node(&view.view(), 0, &mut out);
+    out
+}
+
+fn assert_snapshot(name: &str, view: &impl Renderable<Model>) {
+    let path = format!("{}/fixtures/snapshots/{}.html", env!("CARGO_MANIFEST_DIR"), name);
+    let actual = render(view);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("no snapshot {}; run with UPDATE_SNAPSHOTS=1", path));
+    assert!(actual == expected, "{} changed:\n{}", name, actual);
+}
+
+fn all_perms() -> Perms {
+    Perm::ALL.iter().copied().collect()
+}
+
+fn view_only() -> Perms {
+    vec![Perm::View].into_iter().collect()
+}
+
+fn page<T>(items: Vec<T>, total: usize) -> Page<T> {
+    Page { items, offset: 0, total }
+}
+
+fn list_project(id: ProjectId, name: &str, task_count: usize) -> ListProject {
+    ListProject { id, name: name.into(), task_count }
+}
+
+fn list_worker(id: WorkerId, name: &str, task_count: usize) -> ListWorker {
+    ListWorker { id, name: name.into(), task_count }
+}
+
+fn list_task(id: TaskId, name: &str, status: TaskStatus) -> ListTask {
+    ListTask { id, name: name.into(), status }
+}
+
+fn tasks() -> Vec<ListTask> {
+    vec![
+        list_task(1, "Tiles", TaskStatus::Assigned),
+        list_task(2, "Gutters", TaskStatus::Unassigned),
+    ]
+}
+
+fn task_details(worker: Option<ListWorker>) -> TaskDetails {
+    TaskDetails {
+        id: 2,
+        name: "Gutters".into(),
+        project: list_project(1, "Roof", 2),
+        worker,
+        version: 1,
+    }
+}
+
+#[test]
+fn null_view() {
+    assert_snapshot("null", &NullView);
+}
+
+#[test]
+fn error_view() {
+    let view = ErrorView { message: "Not 200".into(), path: "project/1".into() };
+    assert_snapshot("error", &view);
+}
+
+#[test]
+fn projects_view() {
+    let view = ProjectsView {
+        projects: page(vec![list_project(1, "Roof", 2), list_project(2, "Garden", 0)], 2),
+        list: ListState::default(),
+        input: "Shed".into(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("projects", &view);
+}
+
+#[test]
+fn projects_view_paged() {
+    let view = ProjectsView {
+        projects: Page { items: vec![list_project(51, "Attic", 0)], offset: PAGE_SIZE, total: 120 },
+        list: ListState::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("projects_paged", &view);
+}
+
+#[test]
+fn projects_view_empty() {
+    let view = ProjectsView {
+        projects: page(vec![], 0),
+        list: ListState::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("projects_empty", &view);
+}
+
+#[test]
+fn workers_view() {
+    let view = WorkersView {
+        workers: page(vec![list_worker(1, "Ana", 1), list_worker(2, "Bo", 0)], 2),
+        list: ListState::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("workers", &view);
+}
+
+#[test]
+fn workers_view_empty() {
+    let view = WorkersView {
+        workers: page(vec![], 0),
+        list: ListState::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("workers_empty", &view);
+}
+
+fn roles() -> Vec<Role> {
+    vec![
+        Role { id: "admin".into(), perms: Perm::ALL.to_vec() },
+        Role { id: "viewer".into(), perms: vec![Perm::View] },
+        Role { id: "nobody".into(), perms: vec![] },
+    ]
+}
+
+fn users() -> Vec<User> {
+    let roles = roles();
+    vec![
+        User { id: "local|admin".into(), roles: vec![roles[0].clone()], worker_id: Some(1) },
+        User { id: "local|new".into(), roles: vec![], worker_id: None },
+    ]
+}
+
+#[test]
+fn users_view() {
+    let view = UsersView {
+        users: page(users(), 2),
+        roles: roles(),
+        list: ListState::default(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("users", &view);
+}
+
+#[test]
+fn users_view_empty() {
+    let view = UsersView {
+        users: page(vec![], 0),
+        roles: vec![],
+        list: ListState::default(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("users_empty", &view);
+}
+
+#[test]
+fn project_details_view() {
+    let view = ProjectDetailsView {
+        project: ProjectDetails { id: 1, name: "Roof".into(), tasks: tasks(), version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("project_details", &view);
+}
+
+#[test]
+fn project_details_view_empty() {
+    let view = ProjectDetailsView {
+        project: ProjectDetails { id: 1, name: "Roof".into(), tasks: vec![], version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("project_details_empty", &view);
+}
+
+#[test]
+fn worker_details_view() {
+    let view = WorkerDetailsView {
+        worker: WorkerDetails { id: 1, name: "Ana".into(), tasks: tasks()[..1].to_vec(), version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("worker_details", &view);
+}
+
+#[test]
+fn worker_details_view_empty() {
+    let view = WorkerDetailsView {
+        worker: WorkerDetails { id: 1, name: "Ana".into(), tasks: vec![], version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("worker_details_empty", &view);
+}
+
+#[test]
+fn my_tasks_view() {
+    let view = MyTasksView {
+        worker: WorkerDetails { id: 1, name: "Ana".into(), tasks: tasks(), version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("my_tasks", &view);
+}
+
+#[test]
+fn task_details_view_assigned() {
+    let view = TaskDetailsView {
+        task: task_details(Some(list_worker(1, "Ana", 1))),
+        perms: all_perms(),
+        me: Some(1),
+    };
+    assert_snapshot("task_details_assigned", &view);
+}
+
+#[test]
+fn task_details_view_unassigned() {
+    let view = TaskDetailsView { task: task_details(None), perms: all_perms(), me: Some(1) };
+    assert_snapshot("task_details_unassigned", &view);
+}
+
+#[test]
+fn assign_task_view() {
+    let view = AssignTaskView {
+        task: task_details(None),
+        workers: vec![list_worker(1, "Ana", 1), list_worker(2, "Bo", 0)],
+        perms: all_perms(),
+    };
+    assert_snapshot("assign_task", &view);
+}
+
+#[test]
+fn assign_task_view_no_workers() {
+    let view = AssignTaskView {
+        task: task_details(None),
+        workers: vec![],
+        perms: all_perms(),
+    };
+    assert_snapshot("assign_task_no_workers", &view);
+}
+
+#[test]
+fn conflict_view() {
+    let view = ConflictView {
+        change: Change::TaskAssigned { task_id: 1, worker_id: 2 },
+        theirs: Theirs::Task(task_details(Some(list_worker(1, "Ana", 1)))),
+        perms: all_perms(),
+    };
+    assert_snapshot("conflict", &view);
+}
+
+// Without the perms for them, the create forms and assign links go.
+
+#[test]
+fn projects_view_read_only() {
+    let view = ProjectsView {
+        projects: page(vec![list_project(1, "Roof", 2)], 1),
+        list: ListState::default(),
+        input: String::new(),
+        perms: view_only(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("projects_read_only", &view);
+}
+
+#[test]
+fn workers_view_read_only() {
+    let view = WorkersView {
+        workers: page(vec![list_worker(1, "Ana", 1)], 1),
+        list: ListState::default(),
+        input: String::new(),
+        perms: view_only(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("workers_read_only", &view);
+}
+
+#[test]
+fn project_details_view_read_only() {
+    let view = ProjectDetailsView {
+        project: ProjectDetails { id: 1, name: "Roof".into(), tasks: tasks(), version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        input: String::new(),
+        perms: view_only(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("project_details_read_only", &view);
+}
+
+#[test]
+fn task_details_view_read_only() {
+    let view = TaskDetailsView {
+        task: task_details(Some(list_worker(1, "Ana", 1))),
+        perms: view_only(),
+        me: Some(1),
+    };
+    assert_snapshot("task_details_read_only", &view);
+}
+
+#[test]
+fn assign_task_view_read_only() {
+    let view = AssignTaskView {
+        task: task_details(None),
+        workers: vec![list_worker(1, "Ana", 1)],
+        perms: view_only(),
+    };
+    assert_snapshot("assign_task_read_only", &view);
+}
+
+#[test]
+fn conflict_view_read_only() {
+    let view = ConflictView {
+        change: Change::TaskUnassigned { task_id: 1 },
+        theirs: Theirs::Task(task_details(None)),
+        perms: view_only(),
+    };
+    assert_snapshot("conflict_read_only", &view);
+}
+
+#[test]
+fn users_view_read_only() {
+    let view = UsersView {
+        users: page(users(), 2),
+        roles: roles(),
+        list: ListState::default(),
+        perms: view_only(),
+        layout: Layout::default(),
+    };
+    assert_snapshot("users_read_only", &view);
+}
+
+// Hidden columns and collapsed sections, from the prefs.
+
+fn hide_all() -> Layout {
+    let mut layout = Layout::default();
+    layout.toggle_column(Column::TaskCount);
+    layout.toggle_column(Column::Status);
+    layout.toggle_section(Section::Tasks);
+    layout.toggle_section(Section::Roles);
+    layout
+}
+
+#[test]
+fn projects_view_without_task_counts() {
+    let view = ProjectsView {
+        projects: page(vec![list_project(1, "Roof", 2)], 1),
+        list: ListState::default(),
+        input: String::new(),
+        perms: view_only(),
+        layout: hide_all(),
+    };
+    assert_snapshot("projects_no_task_counts", &view);
+}
+
+#[test]
+fn my_tasks_view_without_status() {
+    let view = MyTasksView {
+        worker: WorkerDetails { id: 1, name: "Ana".into(), tasks: tasks(), version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        layout: hide_all(),
+    };
+    assert_snapshot("my_tasks_no_status", &view);
+}
+
+#[test]
+fn project_details_view_collapsed() {
+    let view = ProjectDetailsView {
+        project: ProjectDetails { id: 1, name: "Roof".into(), tasks: tasks(), version: 1 },
+        list: ListState::default(),
+        window: ListWindow::default(),
+        input: String::new(),
+        perms: all_perms(),
+        layout: hide_all(),
+    };
+    assert_snapshot("project_details_collapsed", &view);
+}
+
+#[test]
+fn users_view_collapsed() {
+    let view = UsersView {
+        users: page(users(), 2),
+        roles: roles(),
+        list: ListState::default(),
+        perms: all_perms(),
+        layout: hide_all(),
+    };
+    assert_snapshot("users_collapsed", &view);
+}
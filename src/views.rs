@@ -1,68 +1,750 @@
+// Everything here is built with html!, so names from the API only ever
+// reach the DOM as text nodes or element properties, never as markup.
+// Keep it that way: no Node::from_html, no string-built attributes or
+// handlers with API data in them.
+
+use yew::prelude::*;
 use crate::domain::*;
-use askama::Template;
+use crate::liststate::*;
+use crate::listwindow::*;
+use crate::model::*;
+use crate::msg::*;
+use crate::prefs::*;
+use crate::cache::*;
+use stdweb::web::{ IElement, Element };
+use stdweb::web::event::{ IEvent, ScrollEvent };
+use stdweb::unstable::TryInto;
+
+// Page position of a paged list.
+#[derive(Debug,Clone,Copy)]
+pub struct Pager {
+    pub index: usize,
+    pub count: usize,
+    pub total: usize,
+}
+
+impl Pager {
+    pub fn new<T>(page: &Page<T>) -> Self {
+        Self {
+            index: page.index(),
+            count: page.count(),
+            total: page.total,
+        }
+    }
+
+    pub fn number(&self) -> usize {
+        self.index + 1
+    }
+
+    pub fn has_prev(&self) -> bool {
+        self.index > 0
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.index + 1 < self.count
+    }
+}
+
+impl Renderable<Model> for Pager {
+    fn view(&self) -> Html<Model> {
+        let prev = self.index.saturating_sub(1);
+        let next = self.index + 1;
+
+        html! {
+            <div>
+                <button disabled=!self.has_prev() onclick=|_| Msg::GetPage(prev)>{ "« Prev" }</button>
+                { format!(" Page {} of {} ({} total) ", self.number(), self.count, self.total) }
+                <button disabled=!self.has_next() onclick=|_| Msg::GetPage(next)>{ "Next »" }</button>
+            </div>
+        }
+    }
+}
 
-#[derive(Template)]
-#[template(path = "null.html")]
 pub struct NullView;
 
-#[derive(Template)]
-#[template(path = "projects.html")]
+impl Renderable<Model> for NullView {
+    fn view(&self) -> Html<Model> {
+        html! {
+            <div>{ "(no view)" }</div>
+        }
+    }
+}
+
+// Shown in place of the scene when a request or render fails.
+// Keeps the path it failed on so routing back to it refetches.
+pub struct ErrorView {
+    pub message: String,
+    pub path: String,
+}
+
+impl Renderable<Model> for ErrorView {
+    fn view(&self) -> Html<Model> {
+        html! {
+            <div>
+                <h2>{ "Something went wrong" }</h2>
+                <p>{ &self.message }</p>
+                <button onclick=|_| Msg::Retry>{ "Retry" }</button>
+            </div>
+        }
+    }
+}
+
 pub struct ProjectsView {
-    pub projects: Vec<ListProject>,
+    pub projects: Page<ListProject>,
+    pub list: ListState,
+    pub input: String,
+    pub perms: Perms,
+    pub layout: Layout,
+}
+
+impl ProjectsView {
+    pub fn rows(&self) -> Vec<&ListProject> {
+        self.list.apply(&self.projects.items)
+    }
+
+    pub fn pager(&self) -> Pager {
+        Pager::new(&self.projects)
+    }
+}
+
+impl Renderable<Model> for ProjectsView {
+    fn view(&self) -> Html<Model> {
+        let name = self.input.clone();
+
+        let form = if self.perms.has(Perm::CreateProject) {
+            html! {
+                <div>
+                    <input placeholder="Project Name"
+                        value=&self.input
+                        oninput=|e| Msg::SetInput(e.value) />
+                    <button onclick=|_| Msg::AddProject(name.clone())>{ "Add Project" }</button>
+                </div>
+            }
+        }
+        else {
+            html! { <span /> }
+        };
+
+        html! {
+            <div>
+                <h2>{ "All Projects" }</h2>
+                { form }
+                <ul>
+                    { for self.rows().into_iter().map(|project| view_project_row(project, &self.layout)) }
+                </ul>
+                { self.pager().view() }
+            </div>
+        }
+    }
+}
+
+fn view_project_row(project: &ListProject, layout: &Layout) -> Html<Model> {
+    let id = project.id;
+
+    html! {
+        <li>
+            <a href="javascript:void(0)" onclick=|_| Msg::GetProject(id)>{ &project.name }</a>
+            { view_task_count(project.task_count, layout) }
+        </li>
+    }
+}
+
+fn view_task_count(count: usize, layout: &Layout) -> String {
+    if layout.shows(Column::TaskCount) {
+        format!(" ({} tasks)", count)
+    }
+    else {
+        String::new()
+    }
 }
 
-#[derive(Template)]
-#[template(path = "workers.html")]
 pub struct WorkersView {
-    pub workers: Vec<ListWorker>,
+    pub workers: Page<ListWorker>,
+    pub list: ListState,
+    pub input: String,
+    pub perms: Perms,
+    pub layout: Layout,
+}
+
+impl WorkersView {
+    pub fn rows(&self) -> Vec<&ListWorker> {
+        self.list.apply(&self.workers.items)
+    }
+
+    pub fn pager(&self) -> Pager {
+        Pager::new(&self.workers)
+    }
+}
+
+impl Renderable<Model> for WorkersView {
+    fn view(&self) -> Html<Model> {
+        let name = self.input.clone();
+
+        let form = if self.perms.has(Perm::CreateWorker) {
+            html! {
+                <div>
+                    <input placeholder="Worker Name"
+                        value=&self.input
+                        oninput=|e| Msg::SetInput(e.value) />
+                    <button onclick=|_| Msg::AddWorker(name.clone())>{ "Add Worker" }</button>
+                </div>
+            }
+        }
+        else {
+            html! { <span /> }
+        };
+
+        html! {
+            <div>
+                <h2>{ "All Workers" }</h2>
+                { form }
+                <ul>
+                    { for self.rows().into_iter().map(|worker| view_worker_row(worker, &self.layout)) }
+                </ul>
+                { self.pager().view() }
+            </div>
+        }
+    }
+}
+
+fn view_worker_row(worker: &ListWorker, layout: &Layout) -> Html<Model> {
+    let id = worker.id;
+
+    html! {
+        <li>
+            <a href="javascript:void(0)" onclick=|_| Msg::GetWorker(id)>{ &worker.name }</a>
+            { view_task_count(worker.task_count, layout) }
+        </li>
+    }
 }
 
-#[derive(Template)]
-#[template(path = "users.html")]
+// The user admin scene: each user's roles and what they add up to, and
+// every role with its perms.
 pub struct UsersView {
-    pub users: Vec<User>,
+    pub users: Page<User>,
+    pub roles: Vec<Role>,
+    pub list: ListState,
+    pub perms: Perms,
+    pub layout: Layout,
+}
+
+impl UsersView {
+    pub fn rows(&self) -> Vec<&User> {
+        self.list.apply(&self.users.items)
+    }
+
+    pub fn pager(&self) -> Pager {
+        Pager::new(&self.users)
+    }
+
+    fn view_user(&self, user: &User) -> Html<Model> {
+        let can_manage = self.perms.has(Perm::ManageUsers);
+        let grantable: Vec<&Role> = if can_manage {
+            self.roles.iter().filter(|role| !user.has_role(&role.id)).collect()
+        }
+        else {
+            vec![]
+        };
+
+        html! {
+            <li>
+                <b>{ &user.id }</b>
+                { format!(" ({})", perms_text(&user.perms())) }
+                { view_user_worker(user, can_manage) }
+                <ul>
+                    { for user.roles.iter().map(|role| view_user_role(user, role, can_manage)) }
+                    { for grantable.into_iter().map(|role| view_grant(user, role)) }
+                </ul>
+            </li>
+        }
+    }
+}
+
+impl Renderable<Model> for UsersView {
+    fn view(&self) -> Html<Model> {
+        html! {
+            <div>
+                <h2>{ "All Users" }</h2>
+                <ul>
+                    { for self.rows().into_iter().map(|user| self.view_user(user)) }
+                </ul>
+                { self.pager().view() }
+                { view_section(&self.layout, Section::Roles, html! {
+                    <ul>
+                        { for self.roles.iter().map(view_role) }
+                    </ul>
+                }) }
+            </div>
+        }
+    }
+}
+
+fn view_user_worker(user: &User, can_manage: bool) -> Html<Model> {
+    let worker_id = match user.worker_id {
+        Some(id) => id,
+        None     => return html! { <span /> },
+    };
+    let user_id = user.id.clone();
+
+    let unlink = if can_manage {
+        html! {
+            <a href="javascript:void(0)"
+                onclick=|_| Msg::LinkWorker((user_id.clone(), None))>{ "[unlink]" }</a>
+        }
+    }
+    else {
+        html! { <span /> }
+    };
+
+    html! {
+        <span>
+            { " as " }
+            <a href="javascript:void(0)" onclick=|_| Msg::GetWorker(worker_id)>{ format!("worker {}", worker_id) }</a>
+            { "\u{a0}" }
+            { unlink }
+        </span>
+    }
+}
+
+fn view_user_role(user: &User, role: &Role, can_manage: bool) -> Html<Model> {
+    if !can_manage {
+        return html! { <li>{ &role.id }</li> };
+    }
+
+    let user_id = user.id.clone();
+    let role_id = role.id.clone();
+
+    html! {
+        <li>
+            { &role.id }
+            { "\u{a0}" }
+            <a href="javascript:void(0)"
+                onclick=|_| Msg::RevokeRole((user_id.clone(), role_id.clone()))>{ "[revoke]" }</a>
+        </li>
+    }
+}
+
+fn view_grant(user: &User, role: &Role) -> Html<Model> {
+    let user_id = user.id.clone();
+    let role_id = role.id.clone();
+
+    html! {
+        <li>
+            <a href="javascript:void(0)"
+                onclick=|_| Msg::GrantRole((user_id.clone(), role_id.clone()))>{ format!("[grant {}]", role.id) }</a>
+        </li>
+    }
+}
+
+fn view_role(role: &Role) -> Html<Model> {
+    let perms: Perms = role.perms.iter().copied().collect();
+
+    html! {
+        <li>
+            <b>{ &role.id }</b>
+            { format!(": {}", perms_text(&perms)) }
+        </li>
+    }
+}
+
+fn perms_text(perms: &Perms) -> String {
+    let names: Vec<String> = perms.iter().map(|perm| perm.to_string()).collect();
+    if names.is_empty() {
+        "no permissions".into()
+    }
+    else {
+        names.join(", ")
+    }
 }
 
-#[derive(Template)]
-#[template(path = "assign_task.html")]
 pub struct AssignTaskView {
     pub task: TaskDetails,
     pub workers: Vec<ListWorker>,
+    pub perms: Perms,
+}
+
+impl Renderable<Model> for AssignTaskView {
+    fn view(&self) -> Html<Model> {
+        let task_id = self.task.id;
+        let can_assign = self.perms.has(Perm::AssignTask);
+
+        html! {
+            <div>
+                <h2>{ format!("Assign Worker to {}", self.task.name) }</h2>
+                <h3>{ "Workers:" }</h3>
+                <ul>
+                    { for self.workers.iter().map(|worker| view_assign_row(task_id, worker, can_assign)) }
+                </ul>
+            </div>
+        }
+    }
+}
+
+fn view_assign_row(task_id: TaskId, worker: &ListWorker, can_assign: bool) -> Html<Model> {
+    let worker_id = worker.id;
+
+    if !can_assign {
+        return html! { <li>{ &worker.name }</li> };
+    }
+
+    html! {
+        <li>
+            <a href="javascript:void(0)" onclick=|_| Msg::AssignTask((task_id, worker_id))>{ &worker.name }</a>
+        </li>
+    }
 }
 
-#[derive(Template)]
-#[template(path = "project_details.html")]
 pub struct ProjectDetailsView {
     pub project: ProjectDetails,
+    pub list: ListState,
+    pub window: ListWindow,
+    pub input: String,
+    pub perms: Perms,
+    pub layout: Layout,
+}
+
+impl ProjectDetailsView {
+    pub fn rows(&self) -> Vec<&ListTask> {
+        self.list.apply(&self.project.tasks)
+    }
+}
+
+impl Renderable<Model> for ProjectDetailsView {
+    fn view(&self) -> Html<Model> {
+        let name = self.input.clone();
+        let project_id = self.project.id;
+
+        let form = if self.perms.has(Perm::CreateTask) {
+            html! {
+                <div>
+                    <input placeholder="Task Name"
+                        value=&self.input
+                        oninput=|e| Msg::SetInput(e.value) />
+                    <button onclick=|_| Msg::AddTask((name.clone(), project_id))>{ "Add Task" }</button>
+                </div>
+            }
+        }
+        else {
+            html! { <span /> }
+        };
+
+        html! {
+            <div>
+                <h2>{ format!("Project Details for {}", self.project.name) }</h2>
+                { view_section(&self.layout, Section::Tasks, html! {
+                    <div>
+                        { form }
+                        { view_window(&self.window, &self.rows(), |task| view_task_row(task, &self.layout)) }
+                    </div>
+                }) }
+            </div>
+        }
+    }
 }
 
-#[derive(Template)]
-#[template(path = "worker_details.html")]
 pub struct WorkerDetailsView {
     pub worker: WorkerDetails,
+    pub list: ListState,
+    pub window: ListWindow,
+    pub input: String,
+    pub perms: Perms,
+    pub layout: Layout,
+}
+
+impl WorkerDetailsView {
+    pub fn rows(&self) -> Vec<&ListTask> {
+        self.list.apply(&self.worker.tasks)
+    }
+}
+
+impl Renderable<Model> for WorkerDetailsView {
+    fn view(&self) -> Html<Model> {
+        let user_id = self.input.clone();
+        let worker_id = self.worker.id;
+
+        let form = if self.perms.has(Perm::ManageUsers) {
+            html! {
+                <div>
+                    <input placeholder="User Id"
+                        value=&self.input
+                        oninput=|e| Msg::SetInput(e.value) />
+                    <button onclick=|_| Msg::LinkWorker((user_id.clone(), Some(worker_id)))>{ "Link User" }</button>
+                </div>
+            }
+        }
+        else {
+            html! { <span /> }
+        };
+
+        html! {
+            <div>
+                <h2>{ format!("Worker Details for {}", self.worker.name) }</h2>
+                { form }
+                { view_section(&self.layout, Section::Tasks,
+                    view_window(&self.window, &self.rows(), |task| view_task_row(task, &self.layout))) }
+            </div>
+        }
+    }
+}
+
+// The logged-in user's own worker record.
+pub struct MyTasksView {
+    pub worker: WorkerDetails,
+    pub list: ListState,
+    pub window: ListWindow,
+    pub layout: Layout,
+}
+
+impl MyTasksView {
+    pub fn rows(&self) -> Vec<&ListTask> {
+        self.list.apply(&self.worker.tasks)
+    }
+}
+
+impl Renderable<Model> for MyTasksView {
+    fn view(&self) -> Html<Model> {
+        html! {
+            <div>
+                <h2>{ format!("My Tasks ({})", self.worker.name) }</h2>
+                { view_window(&self.window, &self.rows(), |task| view_task_row(task, &self.layout)) }
+            </div>
+        }
+    }
 }
 
-#[derive(Template)]
-#[template(path = "task_details.html")]
 pub struct TaskDetailsView {
     pub task: TaskDetails,
+    pub perms: Perms,
+    // The logged-in user's worker, for "assign to me".
+    pub me: Option<WorkerId>,
+}
+
+impl Renderable<Model> for TaskDetailsView {
+    fn view(&self) -> Html<Model> {
+        let project_id = self.task.project.id;
+
+        html! {
+            <div>
+                <h2>{ format!("Task Details for {}", self.task.name) }</h2>
+                { "Project: " }
+                <a href="javascript:void(0)" onclick=|_| Msg::GetProject(project_id)>{ &self.task.project.name }</a>
+                <br />
+                { "Worker: " }
+                { view_task_worker(&self.task.worker) }
+                { self.view_actions() }
+            </div>
+        }
+    }
+}
+
+impl TaskDetailsView {
+    fn view_actions(&self) -> Html<Model> {
+        let task_id = self.task.id;
+
+        if !self.perms.has(Perm::AssignTask) {
+            return html! { <span /> };
+        }
+
+        let assign_to_me = match self.me {
+            Some(me) if self.task.worker.as_ref().map(|w| w.id) != Some(me) => html! {
+                <span>
+                    { "\u{a0}" }
+                    <a href="javascript:void(0)" onclick=|_| Msg::AssignTask((task_id, me))>{ "[assign to me]" }</a>
+                </span>
+            },
+            _ => html! { <span /> },
+        };
+
+        html! {
+            <span>
+                { "\u{a0}" }
+                <a href="javascript:void(0)" onclick=|_| Msg::PreViewAssignTask(task_id)>{ "[assign]" }</a>
+                { assign_to_me }
+                { "\u{a0}" }
+                <a href="javascript:void(0)" onclick=|_| Msg::UnassignTask(task_id)>{ "[unassign]" }</a>
+            </span>
+        }
+    }
+}
+
+fn view_task_worker(worker: &Option<ListWorker>) -> Html<Model> {
+    match worker {
+        Some(worker) => {
+            let id = worker.id;
+            html! {
+                <a href="javascript:void(0)" onclick=|_| Msg::GetWorker(id)>{ &worker.name }</a>
+            }
+        }
+        None => html! {
+            <span>{ "unassigned" }</span>
+        },
+    }
+}
+
+// Renders only the rows in the window's range inside a fixed-height
+// scroll container, padding out the rest so the scrollbar is right.
+// Yew patches the existing <li>s in place as the window moves instead
+// of building new ones.
+fn view_window<T, F>(window: &ListWindow, rows: &[&T], view_row: F) -> Html<Model>
+    where F: Fn(&T) -> Html<Model>
+{
+    let len = rows.len();
+    let container = format!(
+        "height: {}px; overflow-y: auto;",
+        ListWindow::viewport_height()
+        );
+    let list = format!(
+        "margin: 0; padding-top: {}px; padding-bottom: {}px;",
+        window.pad_top(len),
+        window.pad_bottom(len)
+        );
+
+    html! {
+        <div style=container onscroll=|e| Msg::ScrollList(scroll_top(e))>
+            <ul style=list>
+                { for rows[window.range(len)].iter().map(|row| view_row(row)) }
+            </ul>
+        </div>
+    }
+}
+
+fn scroll_top(event: ScrollEvent) -> usize {
+    event.target()
+        .and_then(|target| target.try_into().ok())
+        .map(|element: Element| element.scroll_top() as usize)
+        .unwrap_or(0)
+}
+
+fn view_task_row(task: &ListTask, layout: &Layout) -> Html<Model> {
+    let id = task.id;
+    let style = ListWindow::row_style();
+    let status = if layout.shows(Column::Status) {
+        format!(" ({})", task.status)
+    }
+    else {
+        String::new()
+    };
+
+    html! {
+        <li style=style>
+            <a href="javascript:void(0)" onclick=|_| Msg::GetTask(id)>{ &task.name }</a>
+            { status }
+        </li>
+    }
+}
+
+// A heading that can be clicked to hide or show what's under it.
+fn view_section(layout: &Layout, section: Section, body: Html<Model>) -> Html<Model> {
+    let (marker, body) = if layout.is_open(section) {
+        ("[-]", body)
+    }
+    else {
+        ("[+]", html! { <span /> })
+    };
+    let title = match section {
+        Section::Tasks => "Tasks:",
+        Section::Roles => "Roles",
+    };
+
+    html! {
+        <div>
+            <h3>
+                <a href="javascript:void(0)" onclick=|_| Msg::ToggleSection(section)>{ marker }</a>
+                { format!(" {}", title) }
+            </h3>
+            { body }
+        </div>
+    }
+}
+
+// What the server has now, for a write it turned down because someone
+// else changed the thing first.
+pub enum Theirs {
+    Task(TaskDetails),
+    Project(ProjectDetails),
+}
+
+impl Theirs {
+    pub fn hash_path(&self) -> String {
+        match self {
+            Theirs::Task(task)       => format!("task/{}", task.id),
+            Theirs::Project(project) => format!("project/{}", project.id),
+        }
+    }
+
+    fn summary(&self) -> String {
+        match self {
+            Theirs::Task(task) => match &task.worker {
+                Some(worker) => format!("{} is assigned to {}.", task.name, worker.name),
+                None         => format!("{} is unassigned.", task.name),
+            },
+            Theirs::Project(project) => {
+                let names: Vec<&str> = project.tasks.iter().map(|task| task.name.as_str()).collect();
+                format!("{} has {} tasks: {}.", project.name, names.len(), names.join(", "))
+            }
+        }
+    }
+}
+
+// Shown instead of saving a stale write. Keeping theirs goes to the
+// thing as it is now; applying mine makes the change again against it.
+pub struct ConflictView {
+    pub change: Change,
+    pub theirs: Theirs,
+    pub perms: Perms,
+}
+
+impl Renderable<Model> for ConflictView {
+    fn view(&self) -> Html<Model> {
+        let change = self.change.clone();
+        let keep = match &self.theirs {
+            Theirs::Task(task) => {
+                let id = task.id;
+                html! { <button onclick=|_| Msg::GetTask(id)>{ "Keep theirs" }</button> }
+            }
+            Theirs::Project(project) => {
+                let id = project.id;
+                html! { <button onclick=|_| Msg::GetProject(id)>{ "Keep theirs" }</button> }
+            }
+        };
+        let overwrite = if self.perms.has(change.perm()) {
+            html! { <button onclick=|_| Msg::Overwrite(change.clone())>{ "Apply mine anyway" }</button> }
+        }
+        else {
+            html! { <span /> }
+        };
+
+        html! {
+            <div>
+                <h2>{ "Someone else got there first" }</h2>
+                <p>{ format!("Nothing was saved: this changed while you were {}.", self.change.describe()) }</p>
+                <p>{ self.theirs.summary() }</p>
+                { keep }
+                { overwrite }
+            </div>
+        }
+    }
 }
 
 pub enum Scene {
     Null,
+    Error(ErrorView),
     Projects(ProjectsView),
     Workers(WorkersView),
     Users(UsersView),
     ProjectDetails(ProjectDetailsView),
     WorkerDetails(WorkerDetailsView),
+    MyTasks(MyTasksView),
     TaskDetails(TaskDetailsView),
     AssignTask(AssignTaskView),
+    Conflict(ConflictView),
 }
 
 impl Scene {
     pub fn hash_path(&self) -> String {
         match self {
             Scene::Null          => "".into(),
+            Scene::Error(view)   => view.path.clone(),
             Scene::Projects(_)   => "projects".into(),
             Scene::Workers(_)    => "workers".into(),
             Scene::Users(_)      => "users".into(),
@@ -70,11 +752,398 @@ impl Scene {
                 format!("project/{}", view.project.id),
             Scene::WorkerDetails(view) => 
                 format!("worker/{}", view.worker.id),
+            Scene::MyTasks(_)    => "me".into(),
             Scene::TaskDetails(view) => 
                 format!("task/{}", view.task.id),
             Scene::AssignTask(view) =>
                 format!("assign/{}", view.task.id),
+            Scene::Conflict(view) =>
+                view.theirs.hash_path(),
+        }
+    }
+
+    // Hash path plus the list state query, if there is one.
+    pub fn location(&self) -> String {
+        match self.list().map(ListState::to_query) {
+            Some(ref query) if !query.is_empty() =>
+                format!("{}?{}", self.hash_path(), query),
+            _ =>
+                self.hash_path(),
+        }
+    }
+
+    pub fn list(&self) -> Option<&ListState> {
+        match self {
+            Scene::Projects(view)       => Some(&view.list),
+            Scene::Workers(view)        => Some(&view.list),
+            Scene::Users(view)          => Some(&view.list),
+            Scene::ProjectDetails(view) => Some(&view.list),
+            Scene::WorkerDetails(view)  => Some(&view.list),
+            Scene::MyTasks(view)        => Some(&view.list),
+            _                           => None,
+        }
+    }
+
+    pub fn list_mut(&mut self) -> Option<&mut ListState> {
+        match self {
+            Scene::Projects(view)       => Some(&mut view.list),
+            Scene::Workers(view)        => Some(&mut view.list),
+            Scene::Users(view)          => Some(&mut view.list),
+            Scene::ProjectDetails(view) => Some(&mut view.list),
+            Scene::WorkerDetails(view)  => Some(&mut view.list),
+            Scene::MyTasks(view)        => Some(&mut view.list),
+            _                           => None,
+        }
+    }
+
+    // Text of the scene's create form, if it has one.
+    pub fn input_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Scene::Projects(view)       => Some(&mut view.input),
+            Scene::Workers(view)        => Some(&mut view.input),
+            Scene::ProjectDetails(view) => Some(&mut view.input),
+            Scene::WorkerDetails(view)  => Some(&mut view.input),
+            _                           => None,
+        }
+    }
+
+    // What the current user may do here, for scenes with actions.
+    pub fn perms_mut(&mut self) -> Option<&mut Perms> {
+        match self {
+            Scene::Projects(view)       => Some(&mut view.perms),
+            Scene::Workers(view)        => Some(&mut view.perms),
+            Scene::Users(view)          => Some(&mut view.perms),
+            Scene::ProjectDetails(view) => Some(&mut view.perms),
+            Scene::WorkerDetails(view)  => Some(&mut view.perms),
+            Scene::TaskDetails(view)    => Some(&mut view.perms),
+            Scene::AssignTask(view)     => Some(&mut view.perms),
+            Scene::Conflict(view)       => Some(&mut view.perms),
+            _                           => None,
+        }
+    }
+
+    // The scene's share of the prefs, for scenes with lists.
+    pub fn layout_mut(&mut self) -> Option<&mut Layout> {
+        match self {
+            Scene::Projects(view)       => Some(&mut view.layout),
+            Scene::Workers(view)        => Some(&mut view.layout),
+            Scene::Users(view)          => Some(&mut view.layout),
+            Scene::ProjectDetails(view) => Some(&mut view.layout),
+            Scene::WorkerDetails(view)  => Some(&mut view.layout),
+            Scene::MyTasks(view)        => Some(&mut view.layout),
+            _                           => None,
+        }
+    }
+
+    // Columns that can be hidden in the scene's list.
+    pub fn columns(&self) -> &'static [Column] {
+        match self {
+            Scene::Projects(_)       => &[Column::TaskCount],
+            Scene::Workers(_)        => &[Column::TaskCount],
+            Scene::ProjectDetails(_) => &[Column::Status],
+            Scene::WorkerDetails(_)  => &[Column::Status],
+            Scene::MyTasks(_)        => &[Column::Status],
+            _                        => &[],
+        }
+    }
+
+    // Fits the scene to who is logged in.
+    pub fn set_user(&mut self, perms: &Perms, me: Option<WorkerId>) {
+        if let Some(current) = self.perms_mut() {
+            *current = perms.clone();
+        }
+        if let Scene::TaskDetails(view) = self {
+            view.me = me;
+        }
+    }
+
+    pub fn window(&self) -> Option<&ListWindow> {
+        match self {
+            Scene::ProjectDetails(view) => Some(&view.window),
+            Scene::WorkerDetails(view)  => Some(&view.window),
+            Scene::MyTasks(view)        => Some(&view.window),
+            _                           => None,
+        }
+    }
+
+    pub fn window_mut(&mut self) -> Option<&mut ListWindow> {
+        match self {
+            Scene::ProjectDetails(view) => Some(&mut view.window),
+            Scene::WorkerDetails(view)  => Some(&mut view.window),
+            Scene::MyTasks(view)        => Some(&mut view.window),
+            _                           => None,
+        }
+    }
+
+    // Returns true if the scroll changed which rows are visible.
+    pub fn scroll_list(&mut self, scroll_top: usize) -> bool {
+        match self {
+            Scene::ProjectDetails(view) => {
+                let len = view.rows().len();
+                view.window.scroll_to(scroll_top, len)
+            }
+            Scene::WorkerDetails(view) => {
+                let len = view.rows().len();
+                view.window.scroll_to(scroll_top, len)
+            }
+            Scene::MyTasks(view) => {
+                let len = view.rows().len();
+                view.window.scroll_to(scroll_top, len)
+            }
+            _ => false,
+        }
+    }
+
+    // Whether `other` shows the same things, whatever the state of its
+    // list. False for scenes that aren't compared (see Msg::Polled).
+    pub fn same_data(&self, other: &Scene) -> bool {
+        match (self, other) {
+            (Scene::Projects(a), Scene::Projects(b))             => a.projects == b.projects,
+            (Scene::Workers(a), Scene::Workers(b))               => a.workers == b.workers,
+            (Scene::ProjectDetails(a), Scene::ProjectDetails(b)) => a.project == b.project,
+            (Scene::WorkerDetails(a), Scene::WorkerDetails(b))   => a.worker == b.worker,
+            (Scene::MyTasks(a), Scene::MyTasks(b))               => a.worker == b.worker,
+            (Scene::TaskDetails(a), Scene::TaskDetails(b))       => a.task == b.task,
+            _                                                    => false,
+        }
+    }
+
+    pub fn page_index(&self) -> Option<usize> {
+        match self {
+            Scene::Projects(view) => Some(view.projects.index()),
+            Scene::Workers(view)  => Some(view.workers.index()),
+            Scene::Users(view)    => Some(view.users.index()),
+            _                     => None,
+        }
+    }
+
+    pub fn sort_keys(&self) -> &'static [SortKey] {
+        match self {
+            Scene::Projects(_)       => ListProject::sort_keys(),
+            Scene::Workers(_)        => ListWorker::sort_keys(),
+            Scene::Users(_)          => User::sort_keys(),
+            Scene::ProjectDetails(_) => ListTask::sort_keys(),
+            Scene::WorkerDetails(_)  => ListTask::sort_keys(),
+            Scene::MyTasks(_)        => ListTask::sort_keys(),
+            _                        => &[],
+        }
+    }
+}
+
+impl Renderable<Model> for Scene {
+    fn view(&self) -> Html<Model> {
+        match self {
+            Scene::Null                 => NullView.view(),
+            Scene::Error(view)          => view.view(),
+            Scene::Projects(view)       => view.view(),
+            Scene::Users(view)          => view.view(),
+            Scene::Workers(view)        => view.view(),
+            Scene::ProjectDetails(view) => view.view(),
+            Scene::WorkerDetails(view)  => view.view(),
+            Scene::MyTasks(view)        => view.view(),
+            Scene::TaskDetails(view)    => view.view(),
+            Scene::AssignTask(view)     => view.view(),
+            Scene::Conflict(view)       => view.view(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yew::virtual_dom::VNode;
+
+    const HOSTILE: &[&str] = &[
+        "<script>alert(1)</script>",
+        "\"><img src=x onerror=alert(1)>",
+        "' onmouseover='alert(1)",
+        "\"); alert(1); (\"",
+        "javascript:alert(1)",
+        "</ul></div><h1>pwned</h1>",
+        "{{ task.id }}",
+        "&amp;&lt;&#39;",
+        "\u{202e}gnp.exe",
+        "",
+    ];
+
+    #[derive(Default)]
+    struct Rendered {
+        tags: Vec<String>,
+        attrs: Vec<String>,
+        texts: Vec<String>,
+        raw: usize,
+    }
+
+    fn collect(node: &VNode<Model>, out: &mut Rendered) {
+        match node {
+            VNode::VTag(tag) => {
+                out.tags.push(tag.tag().into());
+                out.attrs.extend(tag.attributes.values().cloned());
+                for child in &tag.children {
+                    collect(child, out);
+                }
+            }
+            VNode::VText(text) => out.texts.push(text.text.clone()),
+            VNode::VList(list) => {
+                for child in &list.children {
+                    collect(child, out);
+                }
+            }
+            VNode::VComp(_) => (),
+            VNode::VRef(_) => out.raw += 1,
+        }
+    }
+
+    fn render(view: &impl Renderable<Model>) -> Rendered {
+        let mut out = Rendered::default();
+        collect(&view.view(), &mut out);
+        out
+    }
+
+    // The name must come out as a text node, verbatim, and nowhere else.
+    fn assert_safe(view: &impl Renderable<Model>, name: &str) {
+        let out = render(view);
+        assert_eq!(out.raw, 0, "raw DOM node rendered for {:?}", name);
+        assert!(out.texts.iter().any(|t| t.contains(name)), "{:?} not shown as text", name);
+        assert!(out.tags.iter().all(|t| t.chars().all(char::is_alphanumeric)), "{:?} became a tag", name);
+        if !name.is_empty() {
+            assert!(out.attrs.iter().all(|a| !a.contains(name)), "{:?} in an attribute", name);
+        }
+    }
+
+    fn task(name: &str) -> ListTask {
+        ListTask { id: 1, name: name.into(), status: TaskStatus::Assigned }
+    }
+
+    fn details(name: &str) -> TaskDetails {
+        TaskDetails {
+            id: 1,
+            name: name.into(),
+            project: ListProject { id: 2, name: name.into(), task_count: 1 },
+            worker: Some(ListWorker { id: 3, name: name.into(), task_count: 1 }),
+            version: 1,
+        }
+    }
+
+    // Every action shown, so every place a name can appear is checked.
+    fn all_perms() -> Perms {
+        Perm::ALL.iter().copied().collect()
+    }
+
+    fn page<T>(items: Vec<T>) -> Page<T> {
+        Page { total: items.len(), items, offset: 0 }
+    }
+
+    #[test]
+    fn projects_view_escapes_names() {
+        for name in HOSTILE {
+            let view = ProjectsView {
+                projects: page(vec![ListProject { id: 1, name: name.to_string(), task_count: 0 }]),
+                list: ListState::default(),
+                input: name.to_string(),
+                perms: all_perms(),
+                layout: Layout::default(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn workers_view_escapes_names() {
+        for name in HOSTILE {
+            let view = WorkersView {
+                workers: page(vec![ListWorker { id: 1, name: name.to_string(), task_count: 0 }]),
+                list: ListState::default(),
+                input: name.to_string(),
+                perms: all_perms(),
+                layout: Layout::default(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn users_view_escapes_ids() {
+        for name in HOSTILE {
+            let role = Role { id: name.to_string(), perms: vec![Perm::View] };
+            let view = UsersView {
+                users: page(vec![User { id: name.to_string(), roles: vec![role.clone()], worker_id: Some(3) }]),
+                roles: vec![role, Role { id: format!("{}2", name), perms: vec![] }],
+                list: ListState::default(),
+                perms: all_perms(),
+                layout: Layout::default(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn project_details_view_escapes_names() {
+        for name in HOSTILE {
+            let view = ProjectDetailsView {
+                project: ProjectDetails { id: 1, name: name.to_string(), tasks: vec![task(name)], version: 1 },
+                list: ListState::default(),
+                window: ListWindow::default(),
+                input: name.to_string(),
+                perms: all_perms(),
+                layout: Layout::default(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn worker_details_view_escapes_names() {
+        for name in HOSTILE {
+            let view = WorkerDetailsView {
+                worker: WorkerDetails { id: 1, name: name.to_string(), tasks: vec![task(name)], version: 1 },
+                list: ListState::default(),
+                window: ListWindow::default(),
+                input: name.to_string(),
+                perms: all_perms(),
+                layout: Layout::default(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn my_tasks_view_escapes_names() {
+        for name in HOSTILE {
+            let view = MyTasksView {
+                worker: WorkerDetails { id: 1, name: name.to_string(), tasks: vec![task(name)], version: 1 },
+                list: ListState::default(),
+                window: ListWindow::default(),
+                layout: Layout::default(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn task_details_view_escapes_names() {
+        for name in HOSTILE {
+            assert_safe(&TaskDetailsView { task: details(name), perms: all_perms(), me: Some(4) }, name);
+        }
+    }
+
+    #[test]
+    fn assign_task_view_escapes_names() {
+        for name in HOSTILE {
+            let view = AssignTaskView {
+                task: details(name),
+                workers: vec![ListWorker { id: 3, name: name.to_string(), task_count: 0 }],
+                perms: all_perms(),
+            };
+            assert_safe(&view, name);
+        }
+    }
+
+    #[test]
+    fn error_view_escapes_message() {
+        for name in HOSTILE {
+            let view = ErrorView { message: name.to_string(), path: "projects".into() };
+            assert_safe(&view, name);
         }
     }
 }
-    